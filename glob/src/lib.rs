@@ -0,0 +1,297 @@
+//! Glob matching shared by `tower-serve-static` and `tower-serve-static-macros`, so that
+//! files filtered at compile time and at runtime are matched the same way.
+//!
+//! You probably want to use `tower-serve-static` instead of this crate.
+//!
+//! Globs match `/`-separated paths. Supported syntax:
+//!
+//! - `?` matches any single character except `/`
+//! - `*` matches any sequence of characters except `/`
+//! - `**` as a whole path segment matches any number of segments
+//! - `[abc]`, `[a-z]` and `[!a-z]` match a single character from (or not from) a set
+//! - `{a,b}` matches either of the comma separated alternatives
+//! - `\` escapes the following character
+
+#![deny(rust_2018_idioms, missing_docs)]
+
+/// A compiled glob pattern.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    alternatives: Vec<Vec<Token>>,
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Char(char),
+    Any,
+    Star,
+    // `**/`, matches the empty string or any sequence of segments ending with `/`.
+    Globstar,
+    // Trailing `**`, matches everything.
+    Rest,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compile a glob pattern. Malformed classes and braces are matched literally.
+    pub fn new(pattern: &str) -> Self {
+        let alternatives = expand_braces(pattern)
+            .iter()
+            .map(|pattern| tokenize(pattern))
+            .collect();
+
+        Self { alternatives }
+    }
+
+    /// Check whether `path` matches the pattern.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = path.chars().collect::<Vec<_>>();
+        self.alternatives
+            .iter()
+            .any(|tokens| match_tokens(tokens, &path))
+    }
+}
+
+/// A glob pattern matched against a path and all of its parent directories.
+///
+/// Patterns without a `/` are matched against the name of each path segment, e.g. `*.map`
+/// matches `js/app.js.map` and `node_modules` matches `node_modules/a/index.js`. Patterns with
+/// a `/` are anchored at the root, so `/_redirects` matches `_redirects` but not
+/// `docs/_redirects`.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    glob: Glob,
+    anchored: bool,
+}
+
+impl Pattern {
+    /// Compile a pattern.
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            glob: Glob::new(pattern.trim_start_matches('/')),
+            anchored: pattern.contains('/'),
+        }
+    }
+
+    /// Check whether the pattern matches the `/`-separated `path` or any of its parent
+    /// directories.
+    pub fn matches(&self, path: &str) -> bool {
+        self.matches_segments(&path.split('/').collect::<Vec<_>>())
+    }
+
+    /// Check whether the pattern matches the path with `segments` or any of its parent
+    /// directories.
+    pub fn matches_segments(&self, segments: &[impl AsRef<str>]) -> bool {
+        (1..=segments.len()).any(|len| {
+            if self.anchored {
+                let prefix = segments[..len]
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.glob.matches(&prefix)
+            } else {
+                self.glob.matches(segments[len - 1].as_ref())
+            }
+        })
+    }
+}
+
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                let mut depth = 0;
+                let mut escaped = false;
+                let mut splits = vec![i];
+                for (j, &c) in chars.iter().enumerate().skip(i) {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '{' => depth += 1,
+                        ',' if depth == 1 => splits.push(j),
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                splits.push(j);
+                                break;
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                if depth != 0 {
+                    // Unbalanced, match the brace literally.
+                    break;
+                }
+
+                let prefix = chars[..i].iter().collect::<String>();
+                let suffix = chars[splits[splits.len() - 1] + 1..]
+                    .iter()
+                    .collect::<String>();
+                return splits
+                    .windows(2)
+                    .flat_map(|w| {
+                        let alternative = chars[w[0] + 1..w[1]].iter().collect::<String>();
+                        expand_braces(&format!("{prefix}{alternative}{suffix}"))
+                    })
+                    .collect();
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+
+    vec![pattern.to_owned()]
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Char(chars[i + 1]));
+                i += 1;
+            }
+            '?' => tokens.push(Token::Any),
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let segment_start = i == 0 || chars[i - 1] == '/';
+                match chars.get(i + 2) {
+                    Some('/') if segment_start => {
+                        tokens.push(Token::Globstar);
+                        i += 2;
+                    }
+                    None if segment_start => {
+                        tokens.push(Token::Rest);
+                        i += 1;
+                    }
+                    _ => {
+                        tokens.push(Token::Star);
+                        i += 1;
+                    }
+                }
+            }
+            '*' => tokens.push(Token::Star),
+            '[' => match parse_class(&chars[i + 1..]) {
+                Some((token, len)) => {
+                    tokens.push(token);
+                    i += len;
+                }
+                None => tokens.push(Token::Char('[')),
+            },
+            c => tokens.push(Token::Char(c)),
+        }
+        i += 1;
+    }
+
+    tokens
+}
+
+// Parse a class after the opening `[`. Returns the token and the number of consumed chars.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+
+    loop {
+        let c = *chars.get(i)?;
+        if c == ']' && !ranges.is_empty() {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+}
+
+fn match_tokens(tokens: &[Token], path: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return path.is_empty();
+    };
+
+    match token {
+        Token::Char(c) => path.first() == Some(c) && match_tokens(rest, &path[1..]),
+        Token::Any => path.first().is_some_and(|&c| c != '/') && match_tokens(rest, &path[1..]),
+        Token::Class { negated, ranges } => {
+            path.first().is_some_and(|&c| {
+                c != '/' && ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }) && match_tokens(rest, &path[1..])
+        }
+        Token::Star => {
+            let segment_len = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=segment_len).any(|skip| match_tokens(rest, &path[skip..]))
+        }
+        Token::Globstar => {
+            match_tokens(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &c)| c == '/')
+                    .any(|(i, _)| match_tokens(rest, &path[i + 1..]))
+        }
+        Token::Rest => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(Glob::new("*.map").matches("app.js.map"));
+        assert!(!Glob::new("*.map").matches("js/app.js.map"));
+        assert!(Glob::new("js/?.js").matches("js/a.js"));
+        assert!(!Glob::new("js/?.js").matches("js/ab.js"));
+        assert!(Glob::new("[a-c]*.txt").matches("b.txt"));
+        assert!(!Glob::new("[!a-c]*.txt").matches("b.txt"));
+    }
+
+    #[test]
+    fn globstar() {
+        let glob = Glob::new("**/*.map");
+        assert!(glob.matches("app.js.map"));
+        assert!(glob.matches("js/vendor/app.js.map"));
+        assert!(!glob.matches("app.js"));
+
+        let glob = Glob::new(".well-known/**");
+        assert!(glob.matches(".well-known/security.txt"));
+        assert!(glob.matches(".well-known/a/b"));
+        assert!(!glob.matches(".well-known"));
+    }
+
+    #[test]
+    fn braces() {
+        let glob = Glob::new("*.{js,css}");
+        assert!(glob.matches("app.js"));
+        assert!(glob.matches("app.css"));
+        assert!(!glob.matches("app.html"));
+        assert!(Glob::new("{a,b{c,d}}").matches("bd"));
+        assert!(Glob::new("{a").matches("{a"));
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(Pattern::new("*.map").matches("js/app.js.map"));
+        assert!(Pattern::new("node_modules").matches("node_modules/a/index.js"));
+        assert!(Pattern::new("node_modules").matches("vendor/node_modules/index.js"));
+        assert!(Pattern::new("/js/*.js").matches("js/app.js"));
+        assert!(!Pattern::new("js/*.js").matches("vendor/js/app.js"));
+        assert!(Pattern::new("/_redirects").matches("_redirects"));
+        assert!(!Pattern::new("/_redirects").matches("docs/_redirects"));
+    }
+}
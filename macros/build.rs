@@ -0,0 +1,42 @@
+//! Track the embedded directories on toolchains that support it, so that adding or removing
+//! files rebuilds the crates that embed them.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+const PROBE: &str = r#"
+#![feature(proc_macro_tracked_path)]
+extern crate proc_macro;
+pub fn probe() {
+    proc_macro::tracked::path("");
+}
+"#;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rustc-check-cfg=cfg(tracked_path)");
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set"));
+    let probe = out_dir.join("probe.rs");
+    fs::write(&probe, PROBE).expect("write probe");
+
+    // Probe instead of checking for a nightly toolchain, the unstable API may change.
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let status = Command::new(rustc)
+        .args([
+            "--edition=2021",
+            "--crate-type=lib",
+            "--emit=metadata",
+            "--out-dir",
+        ])
+        .arg(&out_dir)
+        .arg(&probe)
+        .stderr(Stdio::null())
+        .status();
+    if status.is_ok_and(|status| status.success()) {
+        println!("cargo:rustc-cfg=tracked_path");
+    }
+}
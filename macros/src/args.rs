@@ -0,0 +1,251 @@
+use crate::budget::{Budget, Target};
+use syn::{
+    braced, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, Lit, LitBool, LitStr, Token,
+};
+
+/// Arguments of `include_assets!`.
+pub(crate) struct Args {
+    pub(crate) path: LitStr,
+    pub(crate) include: Vec<Pattern>,
+    pub(crate) exclude: Vec<Pattern>,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) budgets: Vec<Budget>,
+    pub(crate) report_skipped: bool,
+}
+
+/// A glob pattern matched against a path and all of its parent directories.
+pub(crate) struct Pattern {
+    pub(crate) source: String,
+    pattern: tower_serve_static_glob::Pattern,
+}
+
+impl Pattern {
+    pub(crate) fn new(source: &str) -> Self {
+        Self {
+            source: source.to_owned(),
+            pattern: tower_serve_static_glob::Pattern::new(source),
+        }
+    }
+
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        self.pattern.matches(path)
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut args = Args {
+            path: input.parse()?,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_size: None,
+            budgets: Vec::new(),
+            report_skipped: false,
+        };
+
+        let mut seen = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let key = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+
+            if seen.contains(&key) {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("duplicate option `{key}`"),
+                ));
+            }
+
+            match key.to_string().as_str() {
+                "include" => args.include = parse_patterns(input)?,
+                "exclude" => args.exclude = parse_patterns(input)?,
+                "max_file_size" => args.max_file_size = Some(parse_size(input)?),
+                "budget" => args.budgets = parse_budgets(input)?,
+                "report_skipped" => args.report_skipped = input.parse::<LitBool>()?.value,
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!(
+                            "unknown option `{key}`, expected one of `include`, `exclude`, `max_file_size`, `budget`, `report_skipped`"
+                        ),
+                    ))
+                }
+            }
+
+            seen.push(key);
+        }
+
+        Ok(args)
+    }
+}
+
+fn parse_patterns(input: ParseStream<'_>) -> syn::Result<Vec<Pattern>> {
+    let content;
+    bracketed!(content in input);
+    let patterns = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+
+    Ok(patterns
+        .iter()
+        .map(|pattern| Pattern::new(&pattern.value()))
+        .collect())
+}
+
+// Parses `{ total = "2 MiB", "*.js" = brotli("250 KiB"), ... }`.
+fn parse_budgets(input: ParseStream<'_>) -> syn::Result<Vec<Budget>> {
+    let content;
+    braced!(content in input);
+
+    let mut budgets = Vec::new();
+    while !content.is_empty() {
+        let (target, span) = if content.peek(LitStr) {
+            let pattern = content.parse::<LitStr>()?;
+            (
+                Target::Pattern(Pattern::new(&pattern.value())),
+                pattern.span(),
+            )
+        } else {
+            let ident = content.parse::<Ident>()?;
+            if ident != "total" {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `total` or a glob pattern",
+                ));
+            }
+            (Target::Total, ident.span())
+        };
+        content.parse::<Token![=]>()?;
+
+        let brotli = content.peek(Ident);
+        let limit = if brotli {
+            let ident = content.parse::<Ident>()?;
+            if ident != "brotli" {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected a size or `brotli(<size>)`",
+                ));
+            }
+            let size;
+            parenthesized!(size in content);
+            parse_size(&size)?
+        } else {
+            parse_size(&content)?
+        };
+
+        budgets.push(Budget {
+            target,
+            limit,
+            brotli,
+            span,
+        });
+
+        if content.is_empty() {
+            break;
+        }
+        content.parse::<Token![,]>()?;
+    }
+
+    Ok(budgets)
+}
+
+/// Parse a size given as a number of bytes or a string with a unit, e.g. `"250 KiB"`.
+pub(crate) fn parse_size(input: ParseStream<'_>) -> syn::Result<u64> {
+    match input.parse::<Lit>()? {
+        Lit::Int(lit) => lit.base10_parse(),
+        Lit::Str(lit) => size_from_str(&lit.value())
+            .ok_or_else(|| syn::Error::new(lit.span(), "invalid size, expected e.g. \"250 KiB\"")),
+        lit => Err(syn::Error::new(
+            lit.span(),
+            "expected a number of bytes or a string like \"250 KiB\"",
+        )),
+    }
+}
+
+fn size_from_str(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let factor = match unit.trim() {
+        "" | "B" => 1,
+        "KB" | "kB" => 1000,
+        "MB" => 1000 * 1000,
+        "GB" => 1000 * 1000 * 1000,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => return None,
+    };
+
+    let number = number.parse::<f64>().ok()?;
+    Some((number * factor as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(size_from_str("1024"), Some(1024));
+        assert_eq!(size_from_str("250 KiB"), Some(250 * 1024));
+        assert_eq!(size_from_str("1.5MiB"), Some(1536 * 1024));
+        assert_eq!(size_from_str("2 MB"), Some(2_000_000));
+        assert_eq!(size_from_str("2 parsecs"), None);
+        assert_eq!(size_from_str("KiB"), None);
+    }
+
+    #[test]
+    fn args() {
+        let args = syn::parse_str::<Args>(
+            r#""assets", include = ["**/*.js", "*.css"], exclude = [".DS_Store"], max_file_size = "1 MiB","#,
+        )
+        .unwrap();
+
+        assert_eq!(args.path.value(), "assets");
+        assert_eq!(args.include.len(), 2);
+        assert_eq!(args.exclude[0].source, ".DS_Store");
+        assert_eq!(args.max_file_size, Some(1 << 20));
+        assert!(!args.report_skipped);
+        assert!(
+            syn::parse_str::<Args>(r#""assets", report_skipped = true"#)
+                .unwrap()
+                .report_skipped
+        );
+
+        assert!(syn::parse_str::<Args>(r#""assets", unknown = 1"#).is_err());
+        assert!(syn::parse_str::<Args>(r#""assets", budget = { js = 1 }"#).is_err());
+        assert!(syn::parse_str::<Args>(r#""assets", include = [], include = []"#).is_err());
+    }
+
+    #[test]
+    fn budgets() {
+        let args = syn::parse_str::<Args>(
+            r#""assets", budget = { total = "2 MiB", "*.js" = brotli("250 KiB"), }"#,
+        )
+        .unwrap();
+
+        assert!(matches!(args.budgets[0].target, Target::Total));
+        assert_eq!(args.budgets[0].limit, 2 << 20);
+        assert!(!args.budgets[0].brotli);
+        assert!(matches!(&args.budgets[1].target, Target::Pattern(p) if p.source == "*.js"));
+        assert_eq!(args.budgets[1].limit, 250 << 10);
+        assert!(args.budgets[1].brotli);
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(Pattern::new("*.map").matches("js/app.js.map"));
+        assert!(Pattern::new("node_modules").matches("node_modules/a/index.js"));
+        assert!(Pattern::new("/js/*.js").matches("js/app.js"));
+        assert!(!Pattern::new("js/*.js").matches("vendor/js/app.js"));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::args::Pattern;
+use proc_macro2::Span;
+use std::{fmt::Write, path::PathBuf};
+
+/// A size limit for the embedded files, checked at compile time.
+pub(crate) struct Budget {
+    pub(crate) target: Target,
+    pub(crate) limit: u64,
+    // Measure the size after brotli compression.
+    pub(crate) brotli: bool,
+    pub(crate) span: Span,
+}
+
+/// The files a [`Budget`] applies to.
+pub(crate) enum Target {
+    Total,
+    Pattern(Pattern),
+}
+
+/// A file that is embedded.
+pub(crate) struct Asset {
+    pub(crate) path: String,
+    pub(crate) abs: PathBuf,
+    pub(crate) size: u64,
+}
+
+/// Check all budgets, reporting every exceeded budget with the sizes of the files it covers.
+pub(crate) fn check(budgets: &[Budget], assets: &[Asset]) -> syn::Result<()> {
+    let mut compressed = vec![None; assets.len()];
+    let mut error = None::<syn::Error>;
+
+    for budget in budgets {
+        let mut files = Vec::new();
+        for (i, asset) in assets.iter().enumerate() {
+            if let Target::Pattern(pattern) = &budget.target {
+                if !pattern.matches(&asset.path) {
+                    continue;
+                }
+            }
+
+            let size = if budget.brotli {
+                match compressed[i] {
+                    Some(size) => size,
+                    None => {
+                        let size =
+                            brotli_size(asset).map_err(|err| syn::Error::new(budget.span, err))?;
+                        compressed[i] = Some(size);
+                        size
+                    }
+                }
+            } else {
+                asset.size
+            };
+            files.push((asset.path.as_str(), size));
+        }
+
+        let total = files.iter().map(|(_, size)| size).sum::<u64>();
+        if total <= budget.limit {
+            continue;
+        }
+
+        files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let mut message = format!(
+            "asset budget exceeded for {}: {}{}, the limit is {}",
+            match &budget.target {
+                Target::Total => "all files".to_owned(),
+                Target::Pattern(pattern) => format!("`{}`", pattern.source),
+            },
+            format_exact_size(total),
+            if budget.brotli { " after brotli" } else { "" },
+            format_exact_size(budget.limit),
+        );
+        for (path, size) in files {
+            write!(message, "\n  {:>10}  {path}", format_size(size)).unwrap();
+        }
+
+        let budget_error = syn::Error::new(budget.span, message);
+        match &mut error {
+            Some(error) => error.combine(budget_error),
+            None => error = Some(budget_error),
+        }
+    }
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+fn brotli_size(asset: &Asset) -> Result<u64, String> {
+    let contents = std::fs::read(&asset.abs)
+        .map_err(|err| format!("unable to read \"{}\": {err}", asset.abs.display()))?;
+
+    let mut compressed = Vec::new();
+    brotli::BrotliCompress(
+        &mut contents.as_slice(),
+        &mut compressed,
+        &brotli::enc::BrotliEncoderParams::default(),
+    )
+    .map_err(|err| format!("unable to compress \"{}\": {err}", asset.abs.display()))?;
+
+    Ok(compressed.len() as u64)
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        0..=1023 => format!("{size} B"),
+        1024..=1048575 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1048576.0),
+    }
+}
+
+fn format_exact_size(size: u64) -> String {
+    match size {
+        0..=1023 => format_size(size),
+        _ => format!("{} ({size} B)", format_size(size)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets() -> Vec<Asset> {
+        let root =
+            PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("../tests/assets");
+        ["index.html", "text.txt", "image.png"]
+            .into_iter()
+            .map(|path| Asset {
+                path: path.to_owned(),
+                abs: root.join(path),
+                size: std::fs::metadata(root.join(path)).unwrap().len(),
+            })
+            .collect()
+    }
+
+    fn budget(target: Target, limit: u64, brotli: bool) -> Budget {
+        Budget {
+            target,
+            limit,
+            brotli,
+            span: Span::call_site(),
+        }
+    }
+
+    #[test]
+    fn within_budget() {
+        let budgets = [
+            budget(Target::Total, 8147, false),
+            budget(Target::Pattern(Pattern::new("*.html")), 205, false),
+        ];
+        assert!(check(&budgets, &assets()).is_ok());
+    }
+
+    #[test]
+    fn exceeded_budget_lists_files() {
+        let budgets = [budget(Target::Total, 8146, false)];
+        let message = check(&budgets, &assets()).unwrap_err().to_string();
+
+        assert!(message.starts_with(
+            "asset budget exceeded for all files: 8.0 KiB (8147 B), the limit is 8.0 KiB (8146 B)"
+        ));
+        let files = message.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(files.len(), 3);
+        assert!(files[0].ends_with("7.7 KiB  image.png"));
+        assert!(files[2].ends_with("24 B  text.txt"));
+    }
+
+    #[test]
+    fn brotli_budget() {
+        // The html file compresses to well below its size.
+        let budgets = [budget(Target::Pattern(Pattern::new("*.html")), 200, true)];
+        assert!(check(&budgets, &assets()).is_ok());
+
+        let budgets = [budget(Target::Pattern(Pattern::new("*.html")), 32, true)];
+        let message = check(&budgets, &assets()).unwrap_err().to_string();
+        assert!(message.starts_with("asset budget exceeded for `*.html`: "));
+        assert!(message.contains("after brotli, the limit is 32 B"));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{args::Args, embed};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::path::{Path, PathBuf};
+use syn::{
+    parse::{Parse, ParseStream},
+    Expr, ExprGroup, ExprLit, ExprParen, Lit, Token,
+};
+
+pub(crate) fn expand_dir(args: &Args) -> syn::Result<TokenStream> {
+    let (mut assets, tracked) = embed::embedded(args)?;
+    // Sorted by path so that lookups can use a binary search.
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let files = assets
+        .iter()
+        .map(|asset| {
+            let integrity = integrity(quote! { ::tower_serve_static }, &asset.abs)
+                .map_err(|err| syn::Error::new(args.path.span(), err))?;
+            let path = &asset.path;
+            let size = asset.size as usize;
+            Ok(quote! { (#path, #size, #integrity) })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        {
+            #tracked
+            ::tower_serve_static::Digests::new(&[#(#files),*])
+        }
+    })
+}
+
+/// Arguments of the hidden macro used by `include_file!`, the path of the calling crate and
+/// the path of the file relative to `CARGO_MANIFEST_DIR`.
+pub(crate) struct FileArgs {
+    krate: TokenTree,
+    path: Expr,
+}
+
+impl Parse for FileArgs {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let krate = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let path = input.parse()?;
+        Ok(Self { krate, path })
+    }
+}
+
+/// Expands to the `Option<Integrity>` of the file. Only string literals can be read here, other
+/// paths such as `concat!(..)` get no digests.
+pub(crate) fn expand_file(args: &FileArgs) -> syn::Result<TokenStream> {
+    let mut path = &args.path;
+    while let Expr::Group(ExprGroup { expr, .. }) | Expr::Paren(ExprParen { expr, .. }) = path {
+        path = expr;
+    }
+    let Expr::Lit(ExprLit {
+        lit: Lit::Str(path),
+        ..
+    }) = path
+    else {
+        return Ok(quote! { ::std::option::Option::None });
+    };
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(path.span(), "unable to resolve $CARGO_MANIFEST_DIR"))?;
+    // Joined like `concat!(env!("CARGO_MANIFEST_DIR"), $file)` in `include_file!`.
+    let file = PathBuf::from(format!("{manifest_dir}{}", path.value()));
+    let krate = &args.krate;
+    let integrity =
+        integrity(quote! { #krate }, &file).map_err(|err| syn::Error::new(path.span(), err))?;
+    Ok(quote! { ::std::option::Option::Some(#integrity) })
+}
+
+fn integrity(krate: TokenStream, path: &Path) -> Result<TokenStream, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| format!("unable to read \"{}\": {err}", path.display()))?;
+    let sha256 = Sha256::digest(&bytes).to_vec();
+    let sha384 = Sha384::digest(&bytes).to_vec();
+    let sha512 = Sha512::digest(&bytes).to_vec();
+
+    Ok(quote! {
+        #krate::Integrity::new([#(#sha256),*], [#(#sha384),*], [#(#sha512),*])
+    })
+}
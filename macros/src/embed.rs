@@ -0,0 +1,319 @@
+use crate::{
+    args::Args,
+    budget::{self, Asset},
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A file that didn't make it into the embedded directory.
+struct Skipped {
+    path: String,
+    reason: SkipReason,
+}
+
+enum SkipReason {
+    NotIncluded,
+    Excluded(String),
+    TooLarge { size: u64, limit: u64 },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::NotIncluded => write!(f, "not matched by any include pattern"),
+            SkipReason::Excluded(pattern) => write!(f, "excluded by `{pattern}`"),
+            SkipReason::TooLarge { size, limit } => {
+                write!(f, "{size} bytes exceeds the limit of {limit} bytes")
+            }
+        }
+    }
+}
+
+pub(crate) fn expand(args: &Args) -> syn::Result<TokenStream> {
+    let (dir, walk) = walk(args)?;
+
+    if args.report_skipped {
+        let mut report = format!("{} files skipped", walk.skipped.len());
+        for Skipped { path, reason } in &walk.skipped {
+            report.push_str(&format!("\n  `{path}` ({reason})"));
+        }
+        return Err(syn::Error::new(args.path.span(), report));
+    }
+
+    budget::check(&args.budgets, &walk.embedded)?;
+
+    // Embedded files are tracked by their `include_bytes!`.
+    let tracked = track(&walk.tracked)?;
+    Ok(quote! {
+        {
+            use ::tower_serve_static::private::include_dir;
+            #tracked
+            #dir
+        }
+    })
+}
+
+/// The files that `include_assets!` embeds with the same arguments, and the items that make the
+/// calling crate rebuild when their contents change.
+pub(crate) fn embedded(args: &Args) -> syn::Result<(Vec<Asset>, TokenStream)> {
+    let (_, walk) = walk(args)?;
+    let abs = walk.embedded.iter().map(|asset| asset.abs.clone());
+    let tracked = track(&walk.tracked.iter().cloned().chain(abs).collect::<Vec<_>>())?;
+    Ok((walk.embedded, tracked))
+}
+
+/// Unused `include_bytes!` of `files`, so that changing them rebuilds the calling crate. The bytes
+/// don't end up in the binary.
+fn track(files: &[PathBuf]) -> syn::Result<TokenStream> {
+    let files = files
+        .iter()
+        .map(|file| {
+            file.to_str().ok_or_else(|| {
+                syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("\"{}\" is not valid UTF-8", file.display()),
+                )
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! { #(const _: &[u8] = include_bytes!(#files);)* })
+}
+
+fn walk(args: &Args) -> syn::Result<(TokenStream, Walk<'_>)> {
+    let root =
+        resolve_path(&args.path.value()).map_err(|err| syn::Error::new(args.path.span(), err))?;
+    if !root.is_dir() {
+        return Err(syn::Error::new(
+            args.path.span(),
+            format!("\"{}\" is not a directory", root.display()),
+        ));
+    }
+
+    let mut walk = Walk {
+        args,
+        root,
+        skipped: Vec::new(),
+        tracked: Vec::new(),
+        embedded: Vec::new(),
+    };
+    let dir = walk
+        .expand_dir(&walk.root.clone())
+        .map_err(|err| syn::Error::new(args.path.span(), err))?
+        .unwrap_or_else(|| quote! { include_dir::Dir::new("", &[]) });
+
+    Ok((dir, walk))
+}
+
+struct Walk<'a> {
+    args: &'a Args,
+    root: PathBuf,
+    skipped: Vec<Skipped>,
+    // Skipped files that are embedded again if their size changes.
+    tracked: Vec<PathBuf>,
+    embedded: Vec<Asset>,
+}
+
+impl Walk<'_> {
+    // Returns `None` if no file in the directory passed the filters.
+    fn expand_dir(&mut self, path: &Path) -> Result<Option<TokenStream>, String> {
+        // Adding or removing files only changes the directory, which only nightly can track.
+        #[cfg(tracked_path)]
+        proc_macro::tracked::path(path);
+
+        let mut children = path
+            .read_dir()
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(entry?.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| format!("unable to read \"{}\": {err}", path.display()))?;
+        children.sort();
+
+        let mut entries = Vec::new();
+        for child in children {
+            if child.is_dir() {
+                if let Some(tokens) = self.expand_dir(&child)? {
+                    entries.push(quote! { include_dir::DirEntry::Dir(#tokens) });
+                }
+            } else if child.is_file() {
+                let relative = normalize_path(&self.root, &child);
+                let size = child
+                    .metadata()
+                    .map_err(|err| format!("unable to read \"{}\": {err}", child.display()))?
+                    .len();
+
+                let abs = || {
+                    child
+                        .canonicalize()
+                        .map_err(|err| format!("failed to resolve \"{}\": {err}", child.display()))
+                };
+                match filter(self.args, &relative, size) {
+                    Some(reason) => {
+                        if let SkipReason::TooLarge { .. } = reason {
+                            self.tracked.push(abs()?);
+                        }
+                        self.skipped.push(Skipped {
+                            path: relative,
+                            reason,
+                        });
+                    }
+                    None => {
+                        let abs = abs()?;
+                        let tokens = expand_file(&child, &abs, &relative)?;
+                        entries.push(quote! { include_dir::DirEntry::File(#tokens) });
+                        self.embedded.push(Asset {
+                            path: relative,
+                            abs,
+                            size,
+                        });
+                    }
+                }
+            }
+        }
+
+        if entries.is_empty() && path != self.root {
+            return Ok(None);
+        }
+
+        let path = normalize_path(&self.root, path);
+        Ok(Some(quote! {
+            include_dir::Dir::new(#path, {
+                const ENTRIES: &[include_dir::DirEntry<'static>] = &[#(#entries),*];
+                ENTRIES
+            })
+        }))
+    }
+}
+
+fn filter(args: &Args, path: &str, size: u64) -> Option<SkipReason> {
+    if !args.include.is_empty() && !args.include.iter().any(|pattern| pattern.matches(path)) {
+        return Some(SkipReason::NotIncluded);
+    }
+    if let Some(pattern) = args.exclude.iter().find(|pattern| pattern.matches(path)) {
+        return Some(SkipReason::Excluded(pattern.source.clone()));
+    }
+    match args.max_file_size {
+        Some(limit) if size > limit => Some(SkipReason::TooLarge { size, limit }),
+        _ => None,
+    }
+}
+
+fn expand_file(path: &Path, abs: &Path, relative: &str) -> Result<TokenStream, String> {
+    let abs = abs
+        .to_str()
+        .ok_or_else(|| format!("\"{}\" is not valid UTF-8", abs.display()))?;
+
+    let tokens = quote! {
+        include_dir::File::new(#relative, include_bytes!(#abs))
+    };
+
+    Ok(match metadata(path) {
+        Some(metadata) => quote! { #tokens.with_metadata(#metadata) },
+        None => tokens,
+    })
+}
+
+fn metadata(path: &Path) -> Option<TokenStream> {
+    fn to_unix(t: std::time::SystemTime) -> Option<u64> {
+        Some(t.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs())
+    }
+
+    if !cfg!(feature = "metadata") {
+        return None;
+    }
+
+    let meta = path.metadata().ok()?;
+    let accessed = to_unix(meta.accessed().ok()?)?;
+    let created = to_unix(meta.created().ok()?)?;
+    let modified = to_unix(meta.modified().ok()?)?;
+
+    Some(quote! {
+        include_dir::Metadata::new(
+            ::std::time::Duration::from_secs(#accessed),
+            ::std::time::Duration::from_secs(#created),
+            ::std::time::Duration::from_secs(#modified),
+        )
+    })
+}
+
+/// Make sure that paths use the same separator regardless of the host.
+fn normalize_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .expect("path inside root")
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Expand environment variables such as `$CARGO_MANIFEST_DIR` in `raw`.
+pub(crate) fn resolve_path(raw: &str) -> Result<PathBuf, String> {
+    let mut resolved = String::new();
+    let mut rest = raw;
+
+    while let Some(dollar) = rest.find('$') {
+        resolved.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+
+        let len = rest
+            .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+            .unwrap_or(rest.len());
+        let (variable, tail) = rest.split_at(len);
+        if variable.is_empty() || variable.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!("unable to parse a variable from \"${rest}\""));
+        }
+
+        let value =
+            std::env::var(variable).map_err(|_| format!("unable to resolve ${variable}"))?;
+        resolved.push_str(&value);
+        rest = tail;
+    }
+    resolved.push_str(rest);
+
+    Ok(PathBuf::from(resolved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_env_variables() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        assert_eq!(
+            resolve_path("$CARGO_MANIFEST_DIR/assets").unwrap(),
+            PathBuf::from(format!("{manifest_dir}/assets"))
+        );
+        assert_eq!(resolve_path("assets").unwrap(), PathBuf::from("assets"));
+        assert!(resolve_path("$1").is_err());
+        assert!(resolve_path("$TOWER_SERVE_STATIC_UNKNOWN").is_err());
+    }
+
+    #[test]
+    fn filters() {
+        let args = syn::parse_str::<Args>(
+            r#""assets", include = ["*.txt", "*.json"], exclude = ["subfolder"], max_file_size = 16"#,
+        )
+        .unwrap();
+
+        assert!(filter(&args, "text.txt", 16).is_none());
+        assert!(matches!(
+            filter(&args, "text.txt", 17),
+            Some(SkipReason::TooLarge {
+                size: 17,
+                limit: 16
+            })
+        ));
+        assert!(matches!(
+            filter(&args, "index.html", 0),
+            Some(SkipReason::NotIncluded)
+        ));
+        assert!(matches!(
+            filter(&args, "subfolder/data.json", 0),
+            Some(SkipReason::Excluded(pattern)) if pattern == "subfolder"
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! Procedural macros for `tower-serve-static`.
+//!
+//! You probably want to use the re-exports from `tower-serve-static` instead of this crate.
+
+#![deny(rust_2018_idioms, missing_docs)]
+#![cfg_attr(tracked_path, feature(proc_macro_tracked_path))]
+
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+mod args;
+mod budget;
+mod digest;
+mod embed;
+
+/// Embed the contents of a directory, keeping only the files that pass the given filters.
+///
+/// Expands to an [`include_dir::Dir`] that can be used with `ServeDir`. The path is resolved
+/// like in `include_dir!`, so `$CARGO_MANIFEST_DIR` and other environment variables are expanded.
+///
+/// ```rust,ignore
+/// use include_dir::Dir;
+/// use tower_serve_static::{include_assets, ServeDir};
+///
+/// static ASSETS_DIR: Dir<'static> = include_assets!(
+///     "$CARGO_MANIFEST_DIR/frontend/dist",
+///     include = ["**/*.{html,css,js}", "images/**"],
+///     exclude = ["**/*.map", ".DS_Store"],
+///     max_file_size = "1 MiB",
+///     budget = {
+///         total = "2 MiB",
+///         "**/*.js" = brotli("250 KiB"),
+///     },
+/// );
+/// let service = ServeDir::new(&ASSETS_DIR);
+/// ```
+///
+/// # Options
+///
+/// - `include` - only embed files matching at least one of the glob patterns. Embeds all
+///   files if omitted.
+/// - `exclude` - don't embed files matching any of the glob patterns.
+/// - `max_file_size` - don't embed files larger than the given size, either as a number of
+///   bytes or a string with a unit such as `"250 KiB"` or `"1 MB"`.
+/// - `budget` - fail compilation if the embedded files get too large. `total` limits the size of
+///   all files, a glob pattern limits the combined size of the files it matches. Wrap the size in
+///   `brotli(..)` to measure the files after brotli compression. The error lists every file
+///   counted against the exceeded budget, largest first.
+/// - `report_skipped` - set to `true` to fail compilation with a list of the skipped files and
+///   why they were skipped, for checking the filters. Nothing is reported by default.
+///
+/// Patterns use the same syntax as `ServeDir::allow`. Patterns without a `/` are matched
+/// against every file and directory name, so `exclude = ["node_modules"]` skips the whole
+/// directory. Directories left without files are omitted.
+///
+/// Changing a file in the directory rebuilds the crate. Adding or removing files is only noticed
+/// on nightly toolchains, on stable add `println!("cargo:rerun-if-changed=frontend/dist")` to the
+/// build script of the crate.
+///
+/// [`include_dir::Dir`]: https://docs.rs/include_dir/latest/include_dir/struct.Dir.html
+#[proc_macro]
+pub fn include_assets(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as args::Args);
+    embed::expand(&args)
+        .unwrap_or_else(|err| {
+            // Wrap the errors so that the macro is still a valid expression.
+            let err = err.into_compile_error();
+            quote::quote! {{ #err ::core::unreachable!() }}
+        })
+        .into()
+}
+
+/// Compute the SHA-256, SHA-384 and SHA-512 digests of the files embedded by `include_assets!`.
+///
+/// Takes the same arguments as `include_assets!` and expands to a `Digests` table of the files
+/// it embeds, for use with `ServeDir::with_digests`. Files are hashed at compile time, so the
+/// table only contains the digests and no runtime hashing is needed.
+///
+/// ```rust,ignore
+/// use include_dir::Dir;
+/// use tower_serve_static::{include_assets, include_digests, Digests, ServeDir};
+///
+/// static ASSETS_DIR: Dir<'static> = include_assets!("$CARGO_MANIFEST_DIR/frontend/dist");
+/// static DIGESTS: Digests = include_digests!("$CARGO_MANIFEST_DIR/frontend/dist");
+///
+/// let service = ServeDir::new(&ASSETS_DIR).with_digests(&DIGESTS);
+/// ```
+#[proc_macro]
+pub fn include_digests(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as args::Args);
+    digest::expand_dir(&args)
+        .unwrap_or_else(|err| {
+            let err = err.into_compile_error();
+            quote::quote! {{ #err ::core::unreachable!() }}
+        })
+        .into()
+}
+
+/// Compute the digests of a file for `include_file!`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn file_integrity(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as digest::FileArgs);
+    digest::expand_file(&args)
+        .unwrap_or_else(|err| {
+            let err = err.into_compile_error();
+            quote::quote! {{ #err ::core::unreachable!() }}
+        })
+        .into()
+}
@@ -0,0 +1,139 @@
+use crate::html;
+
+/// Configuration that is injected into HTML documents when they are served, e.g. the API URL
+/// of the environment a single page app is deployed to.
+///
+/// Used with [`ServeDir::app_config`](crate::ServeDir::app_config).
+///
+/// ```
+/// # use tower_serve_static::AppConfig;
+/// let api_url = std::env::var("API_URL").unwrap_or_else(|_| "http://localhost:8080".into());
+///
+/// let config = AppConfig::new()
+///     // `<script>window.__APP_CONFIG__ = {"apiUrl":"..."};</script>`
+///     .global("__APP_CONFIG__", format!(r#"{{"apiUrl":"{api_url}"}}"#))
+///     // `<link rel="preconnect" href="%API_URL%">`
+///     .placeholder("%API_URL%", api_url);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AppConfig {
+    globals: Vec<(String, String)>,
+    placeholders: Vec<(String, String)>,
+}
+
+impl AppConfig {
+    /// An empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign `json`, e.g. the output of `serde_json::to_string`, to the global variable `name`.
+    ///
+    /// The assignment is added in a `<script>` right after the `<head>` start tag, or before
+    /// the first script if the document has no `<head>` tag, so it runs before the scripts of
+    /// the app. `<` is escaped so that strings can't close the script.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `name` isn't a JavaScript identifier.
+    pub fn global(mut self, name: &str, json: impl Into<String>) -> Self {
+        assert!(
+            is_identifier(name),
+            "`{name}` isn't a JavaScript identifier"
+        );
+        let json = json.into().replace('<', "\\u003c");
+        self.globals.push((name.to_owned(), json));
+        self
+    }
+
+    /// Replace every occurrence of `token`, e.g. `%API_URL%`, with `value`.
+    ///
+    /// The value is inserted as is, it isn't escaped.
+    pub fn placeholder(mut self, token: &str, value: impl Into<String>) -> Self {
+        if !token.is_empty() {
+            self.placeholders.push((token.to_owned(), value.into()));
+        }
+        self
+    }
+
+    /// The HTML document with the configuration, or `None` if nothing was injected.
+    pub(crate) fn inject(&self, html: &str) -> Option<String> {
+        let mut injected = None::<String>;
+        for (token, value) in &self.placeholders {
+            let text = injected.as_deref().unwrap_or(html);
+            if text.contains(token.as_str()) {
+                injected = Some(text.replace(token.as_str(), value));
+            }
+        }
+
+        if !self.globals.is_empty() {
+            let text = injected.as_deref().unwrap_or(html);
+            let mut script = String::from("<script>");
+            for (name, json) in &self.globals {
+                script.push_str(&format!("window.{name} = {json};"));
+            }
+            script.push_str("</script>");
+
+            let at = html::start_tags(text)
+                .find_map(|tag| {
+                    if tag.is("head") {
+                        Some(tag.range.end)
+                    } else if tag.is("script") {
+                        Some(tag.range.start)
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(text.len());
+            injected = Some(format!("{}{script}{}", &text[..at], &text[at..]));
+        }
+
+        injected
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '_' | '$'))
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inject() {
+        let config = AppConfig::new()
+            .global("__APP_CONFIG__", r#"{"title":"</script>"}"#)
+            .placeholder("%API_URL%", "https://api.example.com");
+
+        assert_eq!(
+            config
+                .inject(r#"<!DOCTYPE html><html><HEAD><link href="%API_URL%"></head>"#)
+                .unwrap(),
+            r#"<!DOCTYPE html><html><HEAD><script>window.__APP_CONFIG__ = {"title":"\u003c/script>"};</script><link href="https://api.example.com"></head>"#
+        );
+        assert_eq!(
+            config
+                .inject(r#"<title>App</title><script src="app.js"></script>"#)
+                .unwrap(),
+            r#"<title>App</title><script>window.__APP_CONFIG__ = {"title":"\u003c/script>"};</script><script src="app.js"></script>"#
+        );
+
+        let placeholders = AppConfig::new().placeholder("%API_URL%", "/api");
+        assert_eq!(
+            placeholders.inject("fetch('%API_URL%/%API_URL%')").unwrap(),
+            "fetch('/api//api')"
+        );
+        assert_eq!(placeholders.inject("<p>nothing to replace</p>"), None);
+    }
+
+    #[test]
+    #[should_panic = "`window.config` isn't a JavaScript identifier"]
+    fn invalid_global() {
+        let _ = AppConfig::new().global("window.config", "{}");
+    }
+}
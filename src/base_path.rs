@@ -0,0 +1,172 @@
+use crate::html;
+use std::borrow::Cow;
+
+// Attributes of HTML elements that contain a URL.
+const URL_ATTRIBUTES: [&str; 5] = ["src", "href", "action", "poster", "formaction"];
+
+/// Rewrites absolute URLs in HTML documents and stylesheets to include a path prefix.
+#[derive(Debug)]
+pub(crate) struct BasePath {
+    prefix: String,
+}
+
+impl BasePath {
+    /// A base path with `prefix`, e.g. `/app`, or `None` for the root.
+    pub(crate) fn new(prefix: &str) -> Option<Self> {
+        let prefix = prefix.trim_matches('/');
+        (!prefix.is_empty()).then(|| Self {
+            prefix: format!("/{prefix}"),
+        })
+    }
+
+    /// `url` with the prefix if it is a path on the same host, e.g. the `Location` of a redirect.
+    pub(crate) fn prefix<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        if is_absolute_path(url) {
+            Cow::Owned(format!("{}{url}", self.prefix))
+        } else {
+            Cow::Borrowed(url)
+        }
+    }
+
+    /// The rewritten HTML document or stylesheet, or `None` if nothing was rewritten.
+    pub(crate) fn rewrite(&self, text: &str, html: bool) -> Option<String> {
+        let mut insertions = Vec::new();
+        if html {
+            html_insertions(text, &mut insertions);
+        } else {
+            css_insertions(text, 0, &mut insertions);
+        }
+        if insertions.is_empty() {
+            return None;
+        }
+
+        insertions.sort_unstable();
+        let mut rewritten = String::with_capacity(text.len() + insertions.len() * 8);
+        let mut last = 0;
+        for at in insertions {
+            rewritten.push_str(&text[last..at]);
+            rewritten.push_str(&self.prefix);
+            last = at;
+        }
+        rewritten.push_str(&text[last..]);
+        Some(rewritten)
+    }
+}
+
+// Whether `url` is a path on the same host, e.g. `/app.js` but not `//cdn.example.com/app.js`.
+fn is_absolute_path(url: &str) -> bool {
+    url.starts_with('/') && !url.starts_with("//")
+}
+
+fn html_insertions(html: &str, insertions: &mut Vec<usize>) {
+    for tag in html::start_tags(html) {
+        for attribute in &tag.attributes {
+            let Some((value, range)) = &attribute.value else {
+                continue;
+            };
+
+            if URL_ATTRIBUTES
+                .iter()
+                .any(|name| attribute.name.eq_ignore_ascii_case(name))
+            {
+                let trimmed = value.trim_start();
+                if is_absolute_path(trimmed) {
+                    insertions.push(range.start + value.len() - trimmed.len());
+                }
+            } else if attribute.name.eq_ignore_ascii_case("srcset") {
+                // e.g. `/small.png 1x, /large.png 2x`
+                let mut offset = 0;
+                for candidate in value.split(',') {
+                    let trimmed = candidate.trim_start();
+                    if is_absolute_path(trimmed) {
+                        insertions.push(range.start + offset + candidate.len() - trimmed.len());
+                    }
+                    offset += candidate.len() + 1;
+                }
+            } else if attribute.name.eq_ignore_ascii_case("style") {
+                css_insertions(value, range.start, insertions);
+            }
+        }
+
+        if let (true, Some(raw_text)) = (tag.is("style"), tag.raw_text) {
+            css_insertions(&html[raw_text.clone()], raw_text.start, insertions);
+        }
+    }
+}
+
+// Find the absolute URLs of `url(..)` and `@import ".."` in `css`, which starts at `offset`.
+fn css_insertions(css: &str, offset: usize, insertions: &mut Vec<usize>) {
+    let lowercase = css.to_ascii_lowercase();
+    for pattern in ["url(", "@import"] {
+        for (start, _) in lowercase.match_indices(pattern) {
+            let mut at = start + pattern.len();
+            at += css[at..].len() - css[at..].trim_start().len();
+            if css[at..].starts_with(['"', '\'']) {
+                at += 1;
+            }
+            if is_absolute_path(&css[at..]) {
+                insertions.push(offset + at);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(text: &str, html: bool) -> Option<String> {
+        BasePath::new("/app/").unwrap().rewrite(text, html)
+    }
+
+    #[test]
+    fn html() {
+        let html = r#"<base href="/">
+<link rel="stylesheet" href="/style.css">
+<script type="module" src='/app.js'></script>
+<script>fetch("/api/not-rewritten")</script>
+<a href="https://example.com/">external</a> <a href="//cdn.example.com/">cdn</a>
+<a href="relative/page.html">relative</a> <a href=/unquoted>unquoted</a>
+<img srcset="/small.png 1x, /large.png 2x, medium.png 1.5x">
+<div style="background: url('/bg.png')"></div>
+<style>@import "/theme.css"; body { background: URL( /body.png ) }</style>"#;
+
+        assert_eq!(
+            rewrite(html, true).unwrap(),
+            r#"<base href="/app/">
+<link rel="stylesheet" href="/app/style.css">
+<script type="module" src='/app/app.js'></script>
+<script>fetch("/api/not-rewritten")</script>
+<a href="https://example.com/">external</a> <a href="//cdn.example.com/">cdn</a>
+<a href="relative/page.html">relative</a> <a href=/app/unquoted>unquoted</a>
+<img srcset="/app/small.png 1x, /app/large.png 2x, medium.png 1.5x">
+<div style="background: url('/app/bg.png')"></div>
+<style>@import "/app/theme.css"; body { background: URL( /app/body.png ) }</style>"#
+        );
+    }
+
+    #[test]
+    fn css() {
+        assert_eq!(
+            rewrite(
+                r#"@font-face { src: url("/fonts/inter.woff2") } a { background: url(data:image/png;base64,AA==) }"#,
+                false
+            )
+            .unwrap(),
+            r#"@font-face { src: url("/app/fonts/inter.woff2") } a { background: url(data:image/png;base64,AA==) }"#
+        );
+        assert_eq!(rewrite("a { color: red }", false), None);
+        assert!(BasePath::new("/").is_none());
+    }
+
+    #[test]
+    fn prefix() {
+        let base_path = BasePath::new("app").unwrap();
+        assert_eq!(base_path.prefix("/docs/?a=b"), "/app/docs/?a=b");
+        assert_eq!(base_path.prefix("//cdn.example.com/"), "//cdn.example.com/");
+        assert_eq!(
+            base_path.prefix("https://example.com/"),
+            "https://example.com/"
+        );
+    }
+}
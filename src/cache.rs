@@ -0,0 +1,45 @@
+use std::{collections::HashMap, path::Path, sync::RwLock};
+
+/// Values derived from the contents of embedded files, computed on first use.
+///
+/// Keyed by the address and length of the contents, which are embedded and never move, and by
+/// the path of the file, since values may depend on it, e.g. because relative paths in the
+/// file are resolved.
+#[derive(Debug)]
+pub(crate) struct ContentCache<T> {
+    entries: RwLock<HashMap<Key, T>>,
+}
+
+// The address and length of the contents, and the path of the file.
+type Key = (usize, usize, &'static Path);
+
+impl<T> Default for ContentCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: RwLock::default(),
+        }
+    }
+}
+
+impl<T: Clone> ContentCache<T> {
+    pub(crate) fn get_or_insert_with_path(
+        &self,
+        path: &'static Path,
+        contents: &'static [u8],
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let key = (contents.as_ptr() as usize, contents.len(), path);
+        if let Some(value) = self.entries.read().unwrap().get(&key) {
+            return value.clone();
+        }
+
+        let value = f();
+        self.entries.write().unwrap().insert(key, value.clone());
+        value
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+}
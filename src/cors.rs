@@ -0,0 +1,180 @@
+use http::{header, HeaderMap, HeaderValue, Method, Request};
+use std::time::Duration;
+
+/// CORS configuration for read-only static assets, e.g. fonts or ES modules loaded from
+/// another origin.
+///
+/// Only `GET` and `HEAD` requests are allowed and credentials are never allowed. Preflight
+/// requests are answered with `204 No Content` instead of the file.
+///
+/// Used with [`ServeDir::cors`](crate::ServeDir::cors).
+#[derive(Clone, Debug)]
+pub struct Cors {
+    origins: Option<Vec<String>>,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    /// Allow requests from any origin, with `Access-Control-Allow-Origin: *`.
+    pub fn any() -> Self {
+        Self {
+            origins: None,
+            max_age: None,
+        }
+    }
+
+    /// Allow requests from the given origins, such as `https://example.com`.
+    ///
+    /// The origin of the request is echoed back if it is allowed, and `Vary: Origin` is added
+    /// so that caches keep responses for different origins apart.
+    pub fn origins<I, S>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            origins: Some(
+                origins
+                    .into_iter()
+                    .map(|origin| origin.as_ref().trim_end_matches('/').to_owned())
+                    .collect(),
+            ),
+            max_age: None,
+        }
+    }
+
+    /// Let clients cache the preflight response for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Add the CORS headers for a request with the headers `req`.
+    pub(crate) fn apply(&self, req: &HeaderMap, headers: &mut HeaderMap) {
+        match &self.origins {
+            None => {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                    HeaderValue::from_static("*"),
+                );
+            }
+            Some(origins) => {
+                headers.append(header::VARY, HeaderValue::from_static("Origin"));
+                if let Some(origin) = req.get(header::ORIGIN).filter(|origin| {
+                    origins
+                        .iter()
+                        .any(|allowed| allowed.as_bytes().eq_ignore_ascii_case(origin.as_bytes()))
+                }) {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+                }
+            }
+        }
+    }
+
+    /// Add the headers answering a preflight request with the headers `req`.
+    pub(crate) fn preflight(&self, req: &HeaderMap, headers: &mut HeaderMap) {
+        self.apply(req, headers);
+        if !headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN) {
+            return;
+        }
+
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, HEAD, OPTIONS"),
+        );
+        // Static files don't care about request headers, so allow whatever was asked for. The
+        // answer depends on the request then, so caches must keep preflights apart.
+        headers.append(
+            header::VARY,
+            HeaderValue::from_static(
+                "Access-Control-Request-Headers, Access-Control-Request-Method",
+            ),
+        );
+        if let Some(requested) = req.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            );
+        }
+    }
+}
+
+/// Whether `req` is a CORS preflight request.
+pub(crate) fn is_preflight<B>(req: &Request<B>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(header::ORIGIN)
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(origin: &'static str) -> HeaderMap {
+        let mut req = HeaderMap::new();
+        req.insert(header::ORIGIN, HeaderValue::from_static(origin));
+        req.insert(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            HeaderValue::from_static("range"),
+        );
+        req
+    }
+
+    #[test]
+    fn any_origin() {
+        let mut headers = HeaderMap::new();
+        Cors::any().apply(&request("https://example.com"), &mut headers);
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert!(!headers.contains_key("vary"));
+    }
+
+    #[test]
+    fn listed_origins() {
+        let cors = Cors::origins(["https://example.com/", "https://app.example.com"])
+            .max_age(Duration::from_secs(600));
+
+        let mut headers = HeaderMap::new();
+        cors.preflight(&request("https://example.com"), &mut headers);
+        assert_eq!(
+            headers["access-control-allow-origin"],
+            "https://example.com"
+        );
+        assert_eq!(
+            headers["access-control-allow-methods"],
+            "GET, HEAD, OPTIONS"
+        );
+        assert_eq!(headers["access-control-allow-headers"], "range");
+        assert_eq!(headers["access-control-max-age"], "600");
+        assert_eq!(
+            headers.get_all("vary").iter().collect::<Vec<_>>(),
+            [
+                "Origin",
+                "Access-Control-Request-Headers, Access-Control-Request-Method"
+            ]
+        );
+
+        let mut headers = HeaderMap::new();
+        cors.preflight(&request("https://evil.example"), &mut headers);
+        assert!(!headers.contains_key("access-control-allow-origin"));
+        assert!(!headers.contains_key("access-control-allow-methods"));
+        assert_eq!(headers.get_all("vary").iter().count(), 1);
+        assert_eq!(headers["vary"], "Origin");
+    }
+
+    #[test]
+    fn any_origin_preflight() {
+        let mut headers = HeaderMap::new();
+        Cors::any().preflight(&request("https://example.com"), &mut headers);
+        assert_eq!(headers["access-control-allow-origin"], "*");
+        assert_eq!(headers["access-control-allow-headers"], "range");
+        assert_eq!(
+            headers["vary"],
+            "Access-Control-Request-Headers, Access-Control-Request-Method"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use http::HeaderValue;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+// `attr-char` of RFC 5987, everything else is percent encoded.
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// A `Content-Disposition: attachment` header value, as described in RFC 6266.
+///
+/// Names that aren't plain ASCII get an ASCII `filename` fallback for old clients and the
+/// exact name in an RFC 5987 encoded `filename*` parameter.
+pub(crate) fn attachment(filename: Option<&str>) -> HeaderValue {
+    let Some(filename) = filename else {
+        return HeaderValue::from_static("attachment");
+    };
+
+    let fallback = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    let mut value = format!(
+        "attachment; filename=\"{}\"",
+        fallback.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    if fallback != filename {
+        value.push_str("; filename*=UTF-8''");
+        value.extend(utf8_percent_encode(filename, ATTR_CHAR));
+    }
+
+    HeaderValue::from_str(&value).expect("encoded content disposition")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attachment_values() {
+        assert_eq!(attachment(None), "attachment");
+        assert_eq!(
+            attachment(Some("report.pdf")),
+            "attachment; filename=\"report.pdf\""
+        );
+        assert_eq!(
+            attachment(Some("my \"best\" report.pdf")),
+            "attachment; filename=\"my \\\"best\\\" report.pdf\""
+        );
+        assert_eq!(
+            attachment(Some("你好世界.txt")),
+            "attachment; filename=\"____.txt\"; \
+             filename*=UTF-8''%E4%BD%A0%E5%A5%BD%E4%B8%96%E7%95%8C.txt"
+        );
+        assert_eq!(
+            attachment(Some("Grüße 100%.csv")),
+            "attachment; filename=\"Gr__e 100%.csv\"; filename*=UTF-8''Gr%C3%BC%C3%9Fe%20100%25.csv"
+        );
+    }
+}
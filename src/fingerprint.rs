@@ -0,0 +1,121 @@
+use crate::cache::ContentCache;
+use http::HeaderValue;
+use std::path::{Path, PathBuf};
+
+/// The number of hex digits of a fingerprint, the first 64 bits of a SHA-256 digest.
+const LEN: usize = 16;
+
+/// `Cache-Control` of fingerprinted paths, their contents never change.
+pub(crate) fn immutable() -> HeaderValue {
+    HeaderValue::from_static("public, max-age=31536000, immutable")
+}
+
+/// Fingerprints of embedded files, computed on first use.
+#[derive(Debug, Default)]
+pub(crate) struct Fingerprints {
+    cache: ContentCache<String>,
+}
+
+impl Fingerprints {
+    /// The fingerprint of the file at `path` with `contents`, e.g. `3f9a1c0b5e7d2a645e7d2a64`, from the
+    /// SHA-256 digest of the bytes it is served with.
+    pub(crate) fn get(
+        &self,
+        path: &'static Path,
+        contents: &'static [u8],
+        sha256: impl FnOnce() -> [u8; 32],
+    ) -> String {
+        self.cache.get_or_insert_with_path(path, contents, || {
+            sha256()[..LEN / 2]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect()
+        })
+    }
+}
+
+/// Insert `fingerprint` into the file name of `path`, before the last extension.
+///
+/// `app.js` becomes `app.3f9a1c0b5e7d2a64.js`, `LICENSE` becomes `LICENSE.3f9a1c0b5e7d2a64`.
+pub(crate) fn insert(path: &str, fingerprint: &str) -> String {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[name_start..].rfind('.').filter(|dot| *dot > 0) {
+        Some(dot) => {
+            let dot = name_start + dot;
+            format!("{}.{fingerprint}{}", &path[..dot], &path[dot..])
+        }
+        None => format!("{path}.{fingerprint}"),
+    }
+}
+
+/// Remove the fingerprint from the file name of `path`, the reverse of [`insert`].
+///
+/// Returns the original path and the fingerprint, or `None` if the name has no fingerprint.
+pub(crate) fn strip(path: &Path) -> Option<(PathBuf, String)> {
+    let name = path.file_name()?.to_str()?;
+    let parts = name.split('.').collect::<Vec<_>>();
+
+    let index = if parts.len() >= 3 && is_fingerprint(parts[parts.len() - 2]) {
+        parts.len() - 2
+    } else if parts.len() >= 2 && is_fingerprint(parts[parts.len() - 1]) {
+        parts.len() - 1
+    } else {
+        return None;
+    };
+
+    let mut original = parts.clone();
+    let fingerprint = original.remove(index).to_owned();
+    if original.concat().is_empty() {
+        return None;
+    }
+    Some((path.with_file_name(original.join(".")), fingerprint))
+}
+
+fn is_fingerprint(part: &str) -> bool {
+    part.len() == LEN && part.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn insert_and_strip() {
+        for (path, fingerprinted) in [
+            ("app.js", "app.3f9a1c0b5e7d2a64.js"),
+            (
+                "assets/archive.tar.gz",
+                "assets/archive.tar.3f9a1c0b5e7d2a64.gz",
+            ),
+            ("LICENSE", "LICENSE.3f9a1c0b5e7d2a64"),
+            ("v1.2/.env", "v1.2/.env.3f9a1c0b5e7d2a64"),
+        ] {
+            assert_eq!(insert(path, "3f9a1c0b5e7d2a64"), fingerprinted);
+            assert_eq!(
+                strip(Path::new(fingerprinted)),
+                Some((PathBuf::from(path), "3f9a1c0b5e7d2a64".to_owned()))
+            );
+        }
+
+        assert_eq!(strip(Path::new("app.js")), None);
+        assert_eq!(strip(Path::new("app.3F9A1C0B5E7D2A64.js")), None);
+        assert_eq!(strip(Path::new("app.3f9a1c0b.js")), None);
+        assert_eq!(strip(Path::new(".3f9a1c0b5e7d2a64")), None);
+    }
+
+    #[test]
+    fn cached() {
+        static CONTENTS: &[u8] = b"foobar";
+        let path = Path::new("foo.txt");
+        let fingerprints = Fingerprints::default();
+        // `printf foobar | sha256sum`
+        let sha256 = || Sha256::digest(CONTENTS).into();
+        assert_eq!(fingerprints.get(path, CONTENTS, sha256), "c3ab8ff13720e8ad");
+        assert_eq!(fingerprints.cache.len(), 1);
+        assert_eq!(
+            fingerprints.get(path, CONTENTS, || unreachable!()),
+            "c3ab8ff13720e8ad"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! A minimal scanner for the start tags of HTML documents.
+//!
+//! This isn't a full HTML parser, it only finds start tags and their attributes, skipping
+//! comments and the contents of raw text elements such as `<script>` and `<style>`.
+
+use std::ops::Range;
+
+/// A start tag, e.g. `<script type="module" src="app.js">`.
+#[derive(Debug)]
+pub(crate) struct Tag<'a> {
+    /// The range of the start tag in the document, from `<` to `>`.
+    pub(crate) range: Range<usize>,
+    /// The name as written in the document, compare with `eq_ignore_ascii_case`.
+    pub(crate) name: &'a str,
+    pub(crate) attributes: Vec<Attribute<'a>>,
+    /// The range of the contents of a raw text element, e.g. the CSS of `<style>`.
+    pub(crate) raw_text: Option<Range<usize>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Attribute<'a> {
+    pub(crate) name: &'a str,
+    /// The value without quotes and its range in the document, `None` for boolean attributes.
+    pub(crate) value: Option<(&'a str, Range<usize>)>,
+}
+
+impl<'a> Tag<'a> {
+    pub(crate) fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    /// The value of the attribute `name`, an empty string for boolean attributes.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name.eq_ignore_ascii_case(name))
+            .map(|attribute| attribute.value.as_ref().map_or("", |(value, _)| *value))
+    }
+}
+
+const RAW_TEXT: [&str; 5] = ["script", "style", "textarea", "title", "xmp"];
+
+/// Iterate over the start tags of `html`.
+pub(crate) fn start_tags(html: &str) -> StartTags<'_> {
+    StartTags { html, pos: 0 }
+}
+
+pub(crate) struct StartTags<'a> {
+    html: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for StartTags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Tag<'a>> {
+        let html = self.html;
+        let bytes = html.as_bytes();
+
+        loop {
+            let start = self.pos + html[self.pos..].find('<')?;
+            let rest = &html[start..];
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                self.pos = comment
+                    .find("-->")
+                    .map_or(html.len(), |end| start + 4 + end + 3);
+                continue;
+            }
+            if !bytes
+                .get(start + 1)
+                .is_some_and(|b| b.is_ascii_alphabetic())
+            {
+                // end tags, doctypes and a plain `<`
+                self.pos = start + 1;
+                continue;
+            }
+
+            let mut pos = start + 1;
+            let name_end = pos
+                + html[pos..]
+                    .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+                    .unwrap_or(html.len() - pos);
+            let name = &html[pos..name_end];
+            pos = name_end;
+
+            let mut attributes = Vec::new();
+            loop {
+                pos += whitespace(&html[pos..]);
+                match bytes.get(pos) {
+                    None => {
+                        self.pos = html.len();
+                        return None;
+                    }
+                    Some(b'>') => {
+                        pos += 1;
+                        break;
+                    }
+                    Some(b'/') => {
+                        pos += 1;
+                        continue;
+                    }
+                    Some(_) => {}
+                }
+
+                let name_end = pos
+                    + html[pos..]
+                        .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+                        .unwrap_or(html.len() - pos);
+                let name = &html[pos..name_end.max(pos + 1)];
+                pos = name_end.max(pos + 1);
+
+                let after_name = pos + whitespace(&html[pos..]);
+                if bytes.get(after_name) != Some(&b'=') {
+                    attributes.push(Attribute { name, value: None });
+                    continue;
+                }
+                pos = after_name + 1;
+                pos += whitespace(&html[pos..]);
+
+                let range = match bytes.get(pos) {
+                    Some(quote @ (b'"' | b'\'')) => {
+                        let value_start = pos + 1;
+                        let value_end = html[value_start..]
+                            .find(*quote as char)
+                            .map_or(html.len(), |end| value_start + end);
+                        pos = (value_end + 1).min(html.len());
+                        value_start..value_end
+                    }
+                    _ => {
+                        let value_start = pos;
+                        pos += html[pos..]
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(html.len() - pos);
+                        value_start..pos
+                    }
+                };
+                attributes.push(Attribute {
+                    name,
+                    value: Some((&html[range.clone()], range)),
+                });
+            }
+
+            let tag_end = pos;
+            let mut raw_text = None;
+            if RAW_TEXT.iter().any(|raw| name.eq_ignore_ascii_case(raw)) {
+                let end = find_end_tag(&html[pos..], name).map_or(html.len(), |end| pos + end);
+                raw_text = Some(pos..end);
+                pos = end;
+            }
+            self.pos = pos;
+
+            return Some(Tag {
+                range: start..tag_end,
+                name,
+                attributes,
+                raw_text,
+            });
+        }
+    }
+}
+
+fn whitespace(s: &str) -> usize {
+    s.len()
+        - s.trim_start_matches(|c: char| c.is_ascii_whitespace())
+            .len()
+}
+
+// Find `</name` case-insensitively.
+fn find_end_tag(s: &str, name: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut from = 0;
+    while let Some(i) = s[from..].find("</").map(|i| from + i) {
+        let candidate = &bytes[i + 2..];
+        if candidate.len() >= name.len()
+            && candidate[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+        {
+            return Some(i);
+        }
+        from = i + 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan() {
+        let html = r#"<!DOCTYPE html>
+<!-- <script src="commented.js"></script> -->
+<LINK rel=stylesheet href='style.css'/>
+<script type="module" src="app.js" defer></script>
+<script>if (a < b) document.write("<img src=x>")</script>
+<img alt="a > b" src = "logo.png">"#;
+
+        let tags = start_tags(html).collect::<Vec<_>>();
+        let names = tags.iter().map(|tag| tag.name).collect::<Vec<_>>();
+        assert_eq!(names, ["LINK", "script", "script", "img"]);
+
+        assert!(tags[0].is("link"));
+        assert_eq!(tags[0].attribute("REL"), Some("stylesheet"));
+        assert_eq!(tags[0].attribute("href"), Some("style.css"));
+        assert_eq!(
+            &html[tags[0].range.clone()],
+            "<LINK rel=stylesheet href='style.css'/>"
+        );
+
+        assert_eq!(tags[1].attribute("src"), Some("app.js"));
+        assert_eq!(tags[1].attribute("defer"), Some(""));
+        assert_eq!(tags[1].attribute("async"), None);
+        // the `<img>` inside the script is skipped
+        assert_eq!(
+            &html[tags[2].raw_text.clone().unwrap()],
+            r#"if (a < b) document.write("<img src=x>")"#
+        );
+
+        assert_eq!(tags[3].attribute("alt"), Some("a > b"));
+        let (src, range) = tags[3].attributes[1].value.clone().unwrap();
+        assert_eq!(src, "logo.png");
+        assert_eq!(&html[range], "logo.png");
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(start_tags("<a href=\"x").count(), 0);
+        assert_eq!(start_tags("<!-- <a>").count(), 0);
+        assert_eq!(start_tags("a < b").count(), 0);
+        assert_eq!(
+            start_tags("<script>let a").next().unwrap().raw_text,
+            Some(8..13)
+        );
+    }
+}
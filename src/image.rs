@@ -0,0 +1,135 @@
+use http::{header, HeaderMap};
+use std::path::{Path, PathBuf};
+
+/// Modern image formats that can replace a `png`, `jpeg` or `gif` image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ImageFormat {
+    Avif,
+    Webp,
+}
+
+impl ImageFormat {
+    // Ordered by preference, the first one usually compresses best.
+    pub(crate) const ALL: [ImageFormat; 2] = [ImageFormat::Avif, ImageFormat::Webp];
+
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "avif",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            ImageFormat::Avif => "image/avif",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    /// The path of the sibling file in this format, if `path` is an image that can be replaced.
+    pub(crate) fn sibling(self, path: &Path) -> Option<PathBuf> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "gif")
+            .then(|| path.with_extension(self.extension()))
+    }
+
+    /// The `Content-Location` of the sibling, relative to the request path `uri_path`.
+    ///
+    /// Relative, so that it stays correct when the service is nested under a prefix.
+    pub(crate) fn location(self, uri_path: &str) -> Option<String> {
+        let name = uri_path.rsplit('/').next()?;
+        let (stem, _) = name.rsplit_once('.')?;
+        Some(format!("{stem}.{}", self.extension()))
+    }
+
+    /// Pick the best of the `available` formats that the client explicitly lists in `Accept`.
+    ///
+    /// Wildcards are ignored, because older browsers send `image/*` without supporting these formats.
+    pub(crate) fn select(headers: &HeaderMap, available: &[ImageFormat]) -> Option<ImageFormat> {
+        let mut best = None::<(ImageFormat, f32)>;
+
+        for (mime, q) in headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let mime = parts.next()?.trim().to_ascii_lowercase();
+                let q = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((mime, q))
+            })
+        {
+            let Some(format) = available.iter().find(|format| format.mime() == mime) else {
+                continue;
+            };
+            let better = match best {
+                None => q > 0.0,
+                Some((current, best_q)) => {
+                    q > best_q || (q == best_q && format.rank() < current.rank())
+                }
+            };
+            if better {
+                best = Some((*format, q));
+            }
+        }
+
+        best.map(|(format, _)| format)
+    }
+
+    fn rank(self) -> usize {
+        Self::ALL.iter().position(|format| *format == self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn sibling() {
+        assert_eq!(
+            ImageFormat::Avif.sibling(Path::new("img/hero.PNG")),
+            Some(PathBuf::from("img/hero.avif"))
+        );
+        assert_eq!(ImageFormat::Webp.sibling(Path::new("hero.svg")), None);
+        assert_eq!(ImageFormat::Webp.sibling(Path::new("hero")), None);
+    }
+
+    #[test]
+    fn location() {
+        assert_eq!(
+            ImageFormat::Webp.location("/img/hero.png"),
+            Some("hero.webp".to_owned())
+        );
+        assert_eq!(ImageFormat::Webp.location("/v1.2/hero"), None);
+    }
+
+    #[test]
+    fn select() {
+        let all = ImageFormat::ALL;
+        let chrome = accept("image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8");
+
+        assert_eq!(ImageFormat::select(&chrome, &all), Some(ImageFormat::Avif));
+        assert_eq!(
+            ImageFormat::select(&chrome, &[ImageFormat::Webp]),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(
+            ImageFormat::select(&accept("image/avif;q=0.5, image/webp"), &all),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(ImageFormat::select(&accept("image/avif;q=0"), &all), None);
+        assert_eq!(ImageFormat::select(&accept("image/*,*/*"), &all), None);
+    }
+}
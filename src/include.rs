@@ -0,0 +1,282 @@
+use crate::security;
+use include_dir::{Dir, File};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    error::Error,
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// An error expanding the server-side includes of an HTML document.
+///
+/// Returned by [`ServeDir::with_includes`](crate::ServeDir::with_includes).
+#[derive(Debug)]
+pub struct IncludeError {
+    file: String,
+    line: usize,
+    message: String,
+}
+
+impl IncludeError {
+    fn new(file: &Path, line: usize, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_string_lossy().replace('\\', "/"),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// The path of the document containing the error, relative to the embedded directory.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// The line containing the error, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl Error for IncludeError {}
+
+/// HTML documents with their includes expanded, by path.
+#[derive(Debug, Default)]
+pub(crate) struct Includes {
+    expanded: HashMap<PathBuf, String>,
+}
+
+impl Includes {
+    /// Expand the includes of every HTML document in `layers`, ordered from the base layer to
+    /// the topmost overlay.
+    pub(crate) fn expand(layers: &[&'static Dir<'static>]) -> Result<Self, IncludeError> {
+        let mut paths = Vec::new();
+        for layer in layers {
+            collect_html(layer, &mut paths);
+        }
+        paths.sort();
+        paths.dedup();
+
+        let mut expander = Expander {
+            layers,
+            done: HashMap::new(),
+            stack: Vec::new(),
+        };
+        let mut expanded = HashMap::new();
+        for path in paths {
+            let file = expander.get_file(&path).expect("collected file");
+            if file.contents_utf8().is_none() {
+                continue;
+            }
+            if let Cow::Owned(text) = expander.expand(&path)? {
+                expanded.insert(path, text);
+            }
+        }
+
+        Ok(Self { expanded })
+    }
+
+    /// The expanded document at `path`, `None` if it includes nothing.
+    pub(crate) fn get(&self, path: &Path) -> Option<&str> {
+        self.expanded.get(path).map(String::as_str)
+    }
+}
+
+fn collect_html(dir: &'static Dir<'static>, paths: &mut Vec<PathBuf>) {
+    for file in dir.files() {
+        if security::is_html(&crate::guess_mime(file.path(), file.contents())) {
+            paths.push(file.path().to_path_buf());
+        }
+    }
+    for dir in dir.dirs() {
+        collect_html(dir, paths);
+    }
+}
+
+struct Expander<'a> {
+    layers: &'a [&'static Dir<'static>],
+    // Expanded documents by path, borrowed if they include nothing.
+    done: HashMap<PathBuf, Cow<'static, str>>,
+    // The documents being expanded, for detecting cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl Expander<'_> {
+    fn get_file(&self, path: &Path) -> Option<&'static File<'static>> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get_file(path))
+    }
+
+    // Expand the document at `path`, which exists.
+    fn expand(&mut self, path: &Path) -> Result<Cow<'static, str>, IncludeError> {
+        if let Some(done) = self.done.get(path) {
+            return Ok(done.clone());
+        }
+
+        let file = self.get_file(path).expect("existing file");
+        let text = file
+            .contents_utf8()
+            .ok_or_else(|| IncludeError::new(path, 1, "file is not valid UTF-8"))?;
+        let directives = directives(text)
+            .map_err(|(at, message)| IncludeError::new(path, line(text, at), message))?;
+        if directives.is_empty() {
+            self.done.insert(path.to_path_buf(), Cow::Borrowed(text));
+            return Ok(Cow::Borrowed(text));
+        }
+
+        self.stack.push(path.to_path_buf());
+        let mut expanded = String::with_capacity(text.len());
+        let mut last = 0;
+        for Directive { range, target } in directives {
+            let error = |message: String| IncludeError::new(path, line(text, range.start), message);
+
+            let included = resolve(path, target)
+                .ok_or_else(|| error(format!("`{target}` is outside of the directory")))?;
+            if let Some(start) = self.stack.iter().position(|p| *p == included) {
+                let cycle = self.stack[start..]
+                    .iter()
+                    .chain([&included])
+                    .map(|p| p.to_string_lossy().replace('\\', "/"))
+                    .collect::<Vec<_>>();
+                return Err(error(format!("include cycle: {}", cycle.join(" -> "))));
+            }
+            if self.get_file(&included).is_none() {
+                return Err(error(format!("included file `{target}` doesn't exist")));
+            }
+
+            expanded.push_str(&text[last..range.start]);
+            expanded.push_str(&self.expand(&included)?);
+            last = range.end;
+        }
+        expanded.push_str(&text[last..]);
+        self.stack.pop();
+
+        let expanded = Cow::<'static, str>::Owned(expanded);
+        self.done.insert(path.to_path_buf(), expanded.clone());
+        Ok(expanded)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Directive<'a> {
+    // The whole comment, from `<!--` to `-->`.
+    range: Range<usize>,
+    target: &'a str,
+}
+
+// Find the include directives of `html`, or the offset and message of an invalid one.
+fn directives(html: &str) -> Result<Vec<Directive<'_>>, (usize, String)> {
+    const START: &str = "<!--#include";
+
+    let mut directives = Vec::new();
+    let mut from = 0;
+    while let Some(start) = html[from..].find(START).map(|i| from + i) {
+        let body_start = start + START.len();
+        let Some(end) = html[body_start..].find("-->").map(|i| body_start + i) else {
+            return Err((start, "unterminated include directive".to_owned()));
+        };
+
+        let body = html[body_start..end].trim();
+        let target = ["virtual", "file"].into_iter().find_map(|name| {
+            let value = body.strip_prefix(name)?.trim_start().strip_prefix('=')?;
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+            let value = &value[1..];
+            value.find(quote).map(|end| &value[..end])
+        });
+        let Some(target) = target.filter(|target| !target.is_empty()) else {
+            return Err((
+                start,
+                "expected `virtual=\"..\"` or `file=\"..\"` in include directive".to_owned(),
+            ));
+        };
+
+        directives.push(Directive {
+            range: start..end + 3,
+            target,
+        });
+        from = end + 3;
+    }
+    Ok(directives)
+}
+
+// Resolve `target` of the document at `path`, `None` if it is outside of the directory.
+fn resolve(path: &Path, target: &str) -> Option<PathBuf> {
+    let (mut resolved, target) = match target.strip_prefix('/') {
+        Some(target) => (PathBuf::new(), target),
+        None => (path.parent().unwrap_or(Path::new("")).to_path_buf(), target),
+    };
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            segment => resolved.push(segment),
+        }
+    }
+    Some(resolved)
+}
+
+fn line(text: &str, at: usize) -> usize {
+    text[..at].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_directives() {
+        let html = "<p>a</p>\n<!--#include virtual=\"/partials/header.html\" -->\n\
+                    <!--#include file='footer.html'-->";
+        let found = directives(html).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].target, "/partials/header.html");
+        assert_eq!(
+            &html[found[0].range.clone()],
+            "<!--#include virtual=\"/partials/header.html\" -->"
+        );
+        assert_eq!(found[1].target, "footer.html");
+
+        assert!(directives("<!-- #include is a plain comment -->")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            directives("a\n<!--#include virtual=\"x.html\"")
+                .unwrap_err()
+                .0,
+            2
+        );
+        assert!(directives("<!--#include src=\"x.html\" -->").is_err());
+    }
+
+    #[test]
+    fn resolve_targets() {
+        let page = Path::new("docs/guide/index.html");
+        assert_eq!(
+            resolve(page, "/partials/header.html"),
+            Some(PathBuf::from("partials/header.html"))
+        );
+        assert_eq!(
+            resolve(page, "../nav.html"),
+            Some(PathBuf::from("docs/nav.html"))
+        );
+        assert_eq!(
+            resolve(page, "./toc.html"),
+            Some(PathBuf::from("docs/guide/toc.html"))
+        );
+        assert_eq!(resolve(page, "../../../secret.html"), None);
+    }
+}
@@ -0,0 +1,265 @@
+use http::{header::HeaderName, HeaderMap, HeaderValue};
+
+/// Digests of an embedded file, computed at compile time.
+///
+/// Used to emit [Subresource Integrity] attributes, e.g.
+/// `<script src="/app.js" integrity="sha384-…">`, see [`ServeDir::integrity`] and
+/// [`File::integrity`].
+///
+/// [Subresource Integrity]: https://developer.mozilla.org/en-US/docs/Web/Security/Subresource_Integrity
+/// [`ServeDir::integrity`]: crate::ServeDir::integrity
+/// [`File::integrity`]: crate::File::integrity
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Integrity {
+    pub(crate) sha256: [u8; 32],
+    pub(crate) sha384: [u8; 48],
+    pub(crate) sha512: [u8; 64],
+}
+
+impl Integrity {
+    #[doc(hidden)]
+    pub const fn new(sha256: [u8; 32], sha384: [u8; 48], sha512: [u8; 64]) -> Self {
+        Self {
+            sha256,
+            sha384,
+            sha512,
+        }
+    }
+
+    /// The SHA-256 integrity metadata, e.g. `sha256-…`.
+    pub fn sha256(&self) -> String {
+        format!("sha256-{}", base64(&self.sha256))
+    }
+
+    /// The SHA-384 integrity metadata, e.g. `sha384-…`.
+    ///
+    /// This is the algorithm recommended for Subresource Integrity.
+    pub fn sha384(&self) -> String {
+        format!("sha384-{}", base64(&self.sha384))
+    }
+
+    /// The SHA-512 integrity metadata, e.g. `sha512-…`.
+    pub fn sha512(&self) -> String {
+        format!("sha512-{}", base64(&self.sha512))
+    }
+}
+
+impl Integrity {
+    /// Add the RFC 9530 `Repr-Digest` header, and `Content-Digest` if the request asks for it
+    /// with `Want-Content-Digest`.
+    ///
+    /// Files are always sent in full and without a content coding, so both digests are the same.
+    /// The algorithm is picked from the `Want-*-Digest` preferences of the request, SHA-256 is
+    /// used by default.
+    pub(crate) fn digest_headers(&self, req: &HeaderMap, headers: &mut HeaderMap) {
+        let want_repr = req.get("want-repr-digest");
+        if let Some(algorithm) = DigestAlgorithm::preferred(want_repr) {
+            headers.insert(
+                HeaderName::from_static("repr-digest"),
+                algorithm.field(self),
+            );
+        }
+
+        let want_content = req.get("want-content-digest");
+        if let Some(algorithm) =
+            want_content.and_then(|want| DigestAlgorithm::preferred(Some(want)))
+        {
+            headers.insert(
+                HeaderName::from_static("content-digest"),
+                algorithm.field(self),
+            );
+        }
+    }
+}
+
+// The algorithms of the RFC 9530 registry that are neither deprecated nor insecure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    const ALL: [(&'static str, Self); 2] = [("sha-256", Self::Sha256), ("sha-512", Self::Sha512)];
+
+    /// Pick the algorithm with the highest weight in a `Want-*-Digest` header, e.g.
+    /// `sha-256=1, sha-512=3`.
+    ///
+    /// Falls back to SHA-256 if the header doesn't name a supported algorithm, unless SHA-256 was
+    /// explicitly refused with a weight of `0`.
+    fn preferred(want: Option<&HeaderValue>) -> Option<Self> {
+        let want = want.and_then(|want| want.to_str().ok()).unwrap_or_default();
+
+        let mut best = None::<(Self, u8)>;
+        let mut sha256_refused = false;
+        for member in want.split(',') {
+            let (key, weight) = member.split_once('=').unwrap_or((member, "1"));
+            let Some((_, algorithm)) = Self::ALL
+                .into_iter()
+                .find(|(name, _)| key.trim().eq_ignore_ascii_case(name))
+            else {
+                continue;
+            };
+            let Ok(weight) = weight.trim().parse::<u8>() else {
+                continue;
+            };
+
+            if weight == 0 {
+                sha256_refused |= algorithm == Self::Sha256;
+            } else if best.is_none_or(|(_, best)| weight >= best) {
+                best = Some((algorithm, weight));
+            }
+        }
+
+        match best {
+            Some((algorithm, _)) => Some(algorithm),
+            None => (!sha256_refused).then_some(Self::Sha256),
+        }
+    }
+
+    // A member of a `Repr-Digest` or `Content-Digest` dictionary.
+    fn field(self, integrity: &Integrity) -> HeaderValue {
+        let value = match self {
+            Self::Sha256 => format!("sha-256=:{}:", base64(&integrity.sha256)),
+            Self::Sha512 => format!("sha-512=:{}:", base64(&integrity.sha512)),
+        };
+        HeaderValue::from_str(&value).expect("base64 is a valid header value")
+    }
+}
+
+impl std::fmt::Debug for Integrity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Integrity").field(&self.sha384()).finish()
+    }
+}
+
+/// The [`Integrity`] of every file embedded by an `include_assets!` invocation, created with
+/// [`include_digests!`](crate::include_digests).
+///
+/// Used with [`ServeDir::with_digests`](crate::ServeDir::with_digests).
+#[derive(Clone, Copy, Debug)]
+pub struct Digests {
+    // Sorted by path.
+    files: &'static [(&'static str, usize, Integrity)],
+}
+
+impl Digests {
+    #[doc(hidden)]
+    pub const fn new(files: &'static [(&'static str, usize, Integrity)]) -> Self {
+        Self { files }
+    }
+
+    /// The digests of the file at `path`, if its size is `size`.
+    ///
+    /// The size guards against a table that was computed with different filters than the
+    /// directory it is used with.
+    pub(crate) fn get(&self, path: &str, size: usize) -> Option<Integrity> {
+        let index = self
+            .files
+            .binary_search_by(|(candidate, _, _)| (*candidate).cmp(path))
+            .ok()?;
+        let (_, expected, integrity) = self.files[index];
+        (expected == size).then_some(integrity)
+    }
+}
+
+/// Standard base64 with padding, as used by Subresource Integrity and RFC 9530 digests.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_encoding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn want_digest_preferences() {
+        let preferred =
+            |want: &'static str| DigestAlgorithm::preferred(Some(&HeaderValue::from_static(want)));
+
+        assert_eq!(
+            DigestAlgorithm::preferred(None),
+            Some(DigestAlgorithm::Sha256)
+        );
+        assert_eq!(
+            preferred("sha-256=1, sha-512=3"),
+            Some(DigestAlgorithm::Sha512)
+        );
+        assert_eq!(
+            preferred("sha-512=2, sha-256=9"),
+            Some(DigestAlgorithm::Sha256)
+        );
+        assert_eq!(preferred("SHA-512"), Some(DigestAlgorithm::Sha512));
+        assert_eq!(preferred("md5=10"), Some(DigestAlgorithm::Sha256));
+        assert_eq!(preferred("sha-256=0, sha-512=0"), None);
+        assert_eq!(preferred("sha-256=0"), None);
+    }
+
+    #[test]
+    fn include_file_integrity() {
+        // `openssl dgst -sha384 -binary tests/assets/text.txt | base64`
+        let integrity = crate::include_file!("/tests/assets/text.txt")
+            .integrity()
+            .unwrap();
+        assert_eq!(
+            integrity.sha256(),
+            "sha256-khdlrqzANsDeMqKRMQxMo+CrQA3spGBB9MFeB93Y3xM="
+        );
+        assert_eq!(
+            integrity.sha384(),
+            "sha384-d5dUWPL8xh6oREvZdRbUEVsEzLWq5S5efJntV9/9Fk7IV7TdcyZYiduhSwpGF8RN"
+        );
+        assert_eq!(
+            integrity.sha512(),
+            "sha512-Is4k7ynPDg89G3TC+psmf1hnz9rioEmhT8F1JeuJN34h4vwgM+HnVGASplG0g//6wJQvjrwmQHjsAoi44oVQZA=="
+        );
+
+        let file = crate::File::new(b"", http::HeaderValue::from_static("text/plain"));
+        assert!(file.integrity().is_none());
+    }
+
+    #[test]
+    fn include_file_paths() {
+        // `$` is part of the file name, not a variable
+        let integrity = crate::include_file!("/tests/special/price$5.txt")
+            .integrity()
+            .unwrap();
+        assert_eq!(
+            integrity.sha256(),
+            "sha256-aUKMebTfFJIiRl6VsWD3wbi+NsoPRFHEmP68aHcm6v8="
+        );
+
+        // paths that aren't string literals get no digests
+        let file = crate::include_file!(concat!("/tests/assets", "/text.txt"));
+        assert!(file.integrity().is_none());
+        let file =
+            crate::include_file_with_mime!(concat!("/tests/assets", "/text.txt"), "text/plain");
+        assert!(file.integrity().is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use http::{header, HeaderMap, HeaderValue};
+use std::path::{Path, PathBuf};
+
+/// Configuration for negotiating between language variants of a file.
+///
+/// A variant is a file with a language tag before its extension, e.g. `index.en.html` and
+/// `index.de.html` are variants of `index.html`. The variant is picked based on, in order:
+///
+/// 1. The language prefix of the path, see [`LanguageNegotiation::path_prefixes`]
+/// 2. The language cookie, see [`LanguageNegotiation::cookie`]
+/// 3. The `Accept-Language` header, respecting q-values
+/// 4. The default language
+///
+/// If none of these has a variant, the file itself (e.g. `index.html`) is served.
+///
+/// Used with [`ServeDir::negotiate_language`](crate::ServeDir::negotiate_language).
+#[derive(Clone, Debug)]
+pub struct LanguageNegotiation {
+    default: String,
+    cookie: Option<String>,
+    path_prefixes: Vec<String>,
+}
+
+impl LanguageNegotiation {
+    /// Create a new [`LanguageNegotiation`] with the given default language.
+    pub fn new(default: &str) -> Self {
+        Self {
+            default: default.to_ascii_lowercase(),
+            cookie: None,
+            path_prefixes: Vec::new(),
+        }
+    }
+
+    /// Prefer the language stored in the cookie with the given name.
+    pub fn cookie(mut self, name: &str) -> Self {
+        self.cookie = Some(name.to_owned());
+        self
+    }
+
+    /// Treat a leading path segment matching one of `languages` as the preferred language.
+    ///
+    /// The prefix is stripped before looking up the file, so `/de/about.html` serves
+    /// `about.de.html`.
+    pub fn path_prefixes<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.path_prefixes = languages
+            .into_iter()
+            .map(|language| language.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Strip a language prefix from `path`, returning the language and the remaining path.
+    pub(crate) fn strip_prefix(&self, path: &Path) -> Option<(String, PathBuf)> {
+        let first = path.iter().next()?;
+        let language = first.to_str()?.to_ascii_lowercase();
+        if !self.path_prefixes.contains(&language) {
+            return None;
+        }
+        let rest = path.strip_prefix(first).ok()?.to_path_buf();
+        Some((language, rest))
+    }
+
+    /// The preferred languages of the request, most preferred first.
+    pub(crate) fn preferences(&self, headers: &HeaderMap, prefix: Option<String>) -> Vec<String> {
+        let mut preferences = Vec::from_iter(prefix);
+
+        if let Some(name) = &self.cookie {
+            preferences.extend(
+                headers
+                    .get_all(header::COOKIE)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .flat_map(|value| value.split(';'))
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.trim_matches('"').to_ascii_lowercase()),
+            );
+        }
+
+        let mut ranges = headers
+            .get_all(header::ACCEPT_LANGUAGE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim().to_ascii_lowercase();
+                let q = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+            })
+            .collect::<Vec<_>>();
+        // stable, so equal q-values keep their order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        preferences.extend(ranges.into_iter().map(|(tag, _)| tag));
+
+        preferences.push(self.default.clone());
+        preferences
+    }
+
+    /// Pick the best of the `available` language tags for the given preferences. Tags are
+    /// compared case-insensitively.
+    pub(crate) fn select(preferences: &[String], available: &[&str]) -> Option<usize> {
+        preferences.iter().find_map(|range| {
+            // exact match
+            available
+                .iter()
+                .position(|tag| tag.eq_ignore_ascii_case(range))
+                .or_else(|| {
+                    // a more specific tag, e.g. `en` matches `en-gb`
+                    available
+                        .iter()
+                        .position(|tag| {
+                            tag.len() > range.len()
+                                && tag.as_bytes()[range.len()] == b'-'
+                                && tag[..range.len()].eq_ignore_ascii_case(range)
+                        })
+                        // a less specific tag, e.g. `en-us` matches `en`
+                        .or_else(|| {
+                            let mut range = range.as_str();
+                            while let Some((shorter, _)) = range.rsplit_once('-') {
+                                range = shorter;
+                                if let Some(i) = available
+                                    .iter()
+                                    .position(|tag| tag.eq_ignore_ascii_case(range))
+                                {
+                                    return Some(i);
+                                }
+                            }
+                            None
+                        })
+                })
+        })
+    }
+
+    /// The `Vary` header for negotiated responses.
+    pub(crate) fn vary(&self) -> HeaderValue {
+        if self.cookie.is_some() {
+            HeaderValue::from_static("Accept-Language, Cookie")
+        } else {
+            HeaderValue::from_static("Accept-Language")
+        }
+    }
+}
+
+/// If `candidate` is a language variant of some file, return the name of that file and the
+/// language tag, e.g. `index.html` and `de` for `index.de.html`.
+pub(crate) fn variant_of(candidate: &str) -> Option<(String, &str)> {
+    let (rest, last) = candidate.rsplit_once('.')?;
+    // `index.de.html`
+    let with_extension = rest
+        .rsplit_once('.')
+        .map(|(stem, _)| format!("{stem}.{last}"));
+    // `LICENSE.de`
+    let without_extension = Some(rest.to_owned());
+
+    [with_extension, without_extension]
+        .into_iter()
+        .flatten()
+        .find_map(|name| variant_tag(&name, candidate).map(|tag| (name, tag)))
+}
+
+/// If `candidate` is a language variant of `name`, return its language tag.
+///
+/// `index.de.html` is a variant of `index.html` and `LICENSE.de` is a variant of `LICENSE`.
+pub(crate) fn variant_tag<'a>(name: &str, candidate: &'a str) -> Option<&'a str> {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
+    };
+
+    let rest = candidate.strip_prefix(stem)?.strip_prefix('.')?;
+    let tag = match ext {
+        Some(ext) => rest.strip_suffix(ext)?.strip_suffix('.')?,
+        None => rest,
+    };
+
+    is_language_tag(tag).then_some(tag)
+}
+
+// `en`, `de-AT`, `zh-Hant-TW`, ...
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_tags() {
+        assert_eq!(variant_tag("index.html", "index.de.html"), Some("de"));
+        assert_eq!(variant_tag("index.html", "index.en-GB.html"), Some("en-GB"));
+        assert_eq!(variant_tag("LICENSE", "LICENSE.fr"), Some("fr"));
+        assert_eq!(variant_tag("index.html", "index.html"), None);
+        assert_eq!(variant_tag("index.html", "index.de.css"), None);
+        assert_eq!(variant_tag("index.html", "index.v2-beta.html"), None);
+    }
+
+    #[test]
+    fn variants() {
+        assert_eq!(
+            variant_of("index.de.html"),
+            Some(("index.html".to_owned(), "de"))
+        );
+        assert_eq!(
+            variant_of("app.min.en-GB.js"),
+            Some(("app.min.js".to_owned(), "en-GB"))
+        );
+        assert_eq!(variant_of("LICENSE.fr"), Some(("LICENSE".to_owned(), "fr")));
+        assert_eq!(variant_of("index.html"), None);
+        assert_eq!(variant_of("README"), None);
+    }
+
+    #[test]
+    fn preferences() {
+        let negotiation = LanguageNegotiation::new("en").cookie("lang");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_static("fr;q=0.5, de-AT, *;q=0.1, it;q=0"),
+        );
+        assert_eq!(
+            negotiation.preferences(&headers, None),
+            ["de-at", "fr", "en"]
+        );
+
+        headers.insert(header::COOKIE, HeaderValue::from_static("a=b; lang=IT"));
+        assert_eq!(
+            negotiation.preferences(&headers, Some("es".to_owned())),
+            ["es", "it", "de-at", "fr", "en"]
+        );
+    }
+
+    #[test]
+    fn select() {
+        let preferences = |prefs: &[&str]| prefs.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        let available = ["en", "de", "fr-CA"];
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["de"]), &available),
+            Some(1)
+        );
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["de-at"]), &available),
+            Some(1)
+        );
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["fr"]), &available),
+            Some(2)
+        );
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["it", "en"]), &available),
+            Some(0)
+        );
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["fr-ca"]), &available),
+            Some(2)
+        );
+        assert_eq!(
+            LanguageNegotiation::select(&preferences(&["it"]), &available),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_prefix() {
+        let negotiation = LanguageNegotiation::new("en").path_prefixes(["en", "de"]);
+
+        assert_eq!(
+            negotiation.strip_prefix(Path::new("de/about.html")),
+            Some(("de".to_owned(), PathBuf::from("about.html")))
+        );
+        assert_eq!(negotiation.strip_prefix(Path::new("fr/about.html")), None);
+    }
+}
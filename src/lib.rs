@@ -42,31 +42,101 @@
 //! # };
 //! ```
 //!
+//! # Serve a Filtered Directory
+//!
+//! [`include_assets!`] works like `include_dir!`, but only embeds the files you want to serve.
+//!
+//! ```
+//! use tower_serve_static::{ServeDir, include_assets};
+//! use include_dir::Dir;
+//!
+//! // Embed the `tests/assets` directory without JSON files and files larger than 4 KiB.
+//! // Add `report_skipped = true` to list the skipped files in a compile error.
+//! static ASSETS_DIR: Dir<'static> = include_assets!(
+//!     "$CARGO_MANIFEST_DIR/tests/assets",
+//!     exclude = ["*.json"],
+//!     max_file_size = "4 KiB",
+//! );
+//! let service = ServeDir::new(&ASSETS_DIR);
+//! ```
+//!
 //! # Features
 //!
 //! This library exposes the following features that can be enabled:
 //!
 //! - `metadata` - enables [`ServeDir`] to include the `Last-Modified` header in the response headers.
 //!   Additionally, it enables responding with a suitable reply for `If-Modified-Since` conditional requests.
+//! - `sniff` - enables guessing the `Content-Type` from the leading magic bytes of a file if its extension
+//!   is unknown (e.g. extensionless files). For [`include_file!`] this is done at compile time. Responses
+//!   additionally include the `X-Content-Type-Options: nosniff` header so browsers don't second-guess us.
+//! - `regex` - enables [`ServeDir::rewrite_regex`] to rewrite request paths with regular expressions.
+//! - `vite` - enables [`ServeDir::vite_manifest`] to resolve the entry points of a Vite build into
+//!   script, stylesheet and module preload tags.
+//! - `csp-nonce` - enables [`ServeDir::csp_nonce`] to add a fresh `Content-Security-Policy` nonce
+//!   to every HTML response.
 
 #![deny(rust_2018_idioms, missing_docs)]
 
+// Allows the macros to refer to `::tower_serve_static` from within the tests of this crate.
+#[cfg(test)]
+extern crate self as tower_serve_static;
+
 #[macro_use]
 mod macros;
 
+mod app_config;
+mod base_path;
+mod cache;
+mod cors;
+mod disposition;
+mod fingerprint;
+mod html;
+mod image;
+mod include;
+mod integrity;
+mod language;
+mod netlify;
+#[cfg(feature = "csp-nonce")]
+mod nonce;
+mod policy;
+mod preload;
+mod response_headers;
+mod rewrite;
+mod security;
 mod serve_dir;
 mod serve_file;
+mod served_asset;
+#[cfg(feature = "sniff")]
+mod sniff;
+#[cfg(feature = "vite")]
+mod vite;
 
 #[doc(hidden)]
 pub mod private {
-    pub use {http, mime, mime_guess};
+    pub use tower_serve_static_macros::file_integrity;
+    pub use {http, include_dir, mime, mime_guess};
+
+    /// Sniff the mime type from the magic bytes, if the `sniff` feature is enabled.
+    pub const fn sniff(bytes: &[u8]) -> Option<&'static str> {
+        #[cfg(feature = "sniff")]
+        {
+            crate::sniff::sniff(bytes)
+        }
+        #[cfg(not(feature = "sniff"))]
+        {
+            let _ = bytes;
+            None
+        }
+    }
 }
 
 use bytes::Bytes;
+use http::HeaderValue;
 use http_body::{Body, Frame};
 use pin_project::pin_project;
 use std::{
     io,
+    path::Path,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -79,15 +149,30 @@ use tokio_util::io::ReaderStream;
 const DEFAULT_CAPACITY: usize = 65536;
 
 pub use self::{
+    app_config::AppConfig,
+    cors::Cors,
+    include::IncludeError,
+    integrity::{Digests, Integrity},
+    language::LanguageNegotiation,
+    netlify::NetlifyRulesError,
+    response_headers::ResolvedFile,
+    security::SecurityHeaders,
     serve_dir::{
-        ResponseBody as ServeDirResponseBody, ResponseFuture as ServeDirResponseFuture, ServeDir,
+        Resolution, ResolvedPath, ResponseBody as ServeDirResponseBody,
+        ResponseFuture as ServeDirResponseFuture, ServeDir,
     },
     serve_file::{
-        File, ResponseBody as ServeFileResponseBody, ResponseFuture as ServeFileResponseFuture,
-        ServeFile,
+        AnyRequest, File, HttpRequest, ResponseBody as ServeFileResponseBody,
+        ResponseFuture as ServeFileResponseFuture, ServeFile,
     },
+    served_asset::{ServeOutcome, ServedAsset},
 };
 
+#[cfg(feature = "vite")]
+pub use self::vite::{ViteAsset, ViteEntry, ViteManifest, ViteManifestError};
+
+pub use tower_serve_static_macros::{include_assets, include_digests};
+
 // NOTE: This could potentially be upstreamed to `http-body`.
 /// Adapter that turns an `impl AsyncRead` to an `impl Body`.
 #[pin_project]
@@ -129,6 +214,15 @@ where
     }
 }
 
+/// Guess the mime type from the file extension, falling back to sniffing the contents.
+fn guess_mime(path: &Path, contents: &[u8]) -> HeaderValue {
+    mime_guess::from_path(path)
+        .first_raw()
+        .or_else(|| private::sniff(contents))
+        .map(HeaderValue::from_static)
+        .unwrap_or_else(|| HeaderValue::from_str(mime::APPLICATION_OCTET_STREAM.as_ref()).unwrap())
+}
+
 #[cfg(feature = "metadata")]
 fn unmodified_since_request_condition<T>(
     file: &include_dir::File<'_>,
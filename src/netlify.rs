@@ -0,0 +1,495 @@
+use http::{header::HeaderName, HeaderMap, HeaderValue, StatusCode};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{error::Error, fmt};
+
+// Characters of a captured value that would change the meaning of the target: the path is
+// matched decoded, so `/posts/a%3Fb` captures `a?b`, which must not start a query.
+const CAPTURE: &AsciiSet = &CONTROLS.add(b'?').add(b'#').add(b'%');
+
+/// An error in a `_redirects` or `_headers` file.
+///
+/// Returned by [`ServeDir::with_netlify_rules`](crate::ServeDir::with_netlify_rules).
+#[derive(Debug)]
+pub struct NetlifyRulesError {
+    file: &'static str,
+    line: usize,
+    message: String,
+}
+
+impl NetlifyRulesError {
+    pub(crate) fn not_utf8(file: &'static str) -> Self {
+        Self {
+            file,
+            line: 1,
+            message: "file is not valid UTF-8".to_owned(),
+        }
+    }
+
+    /// The name of the file containing the error, `_redirects` or `_headers`.
+    pub fn file(&self) -> &str {
+        self.file
+    }
+
+    /// The line containing the error, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for NetlifyRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl Error for NetlifyRulesError {}
+
+/// The parsed `_redirects` and `_headers` rules.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Rules {
+    redirects: Vec<RedirectRule>,
+    headers: Vec<HeaderRule>,
+}
+
+#[derive(Clone, Debug)]
+struct RedirectRule {
+    from: PathPattern,
+    to: String,
+    status: StatusCode,
+    force: bool,
+}
+
+#[derive(Clone, Debug)]
+struct HeaderRule {
+    path: PathPattern,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// A matched redirect rule.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Redirect {
+    /// The target with all placeholders substituted.
+    pub(crate) to: String,
+    pub(crate) status: StatusCode,
+    /// Apply the rule even if the request path exists.
+    pub(crate) force: bool,
+}
+
+impl Redirect {
+    /// Whether this is a rewrite (e.g. `200` or a custom `404` page) rather than a redirect.
+    pub(crate) fn is_rewrite(&self) -> bool {
+        !self.status.is_redirection()
+    }
+}
+
+impl Rules {
+    pub(crate) fn parse(
+        redirects: Option<&str>,
+        headers: Option<&str>,
+    ) -> Result<Self, NetlifyRulesError> {
+        Ok(Self {
+            redirects: redirects
+                .map(parse_redirects)
+                .transpose()?
+                .unwrap_or_default(),
+            headers: headers.map(parse_headers).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    /// The first redirect rule matching the request `path`.
+    pub(crate) fn redirect(&self, path: &str) -> Option<Redirect> {
+        self.redirects.iter().find_map(|rule| {
+            let captures = rule.from.captures(path)?;
+            Some(Redirect {
+                to: substitute(&rule.to, &captures),
+                status: rule.status,
+                force: rule.force,
+            })
+        })
+    }
+
+    /// Append the headers of all rules matching the request `path`.
+    pub(crate) fn apply_headers(&self, path: &str, headers: &mut HeaderMap) {
+        for rule in &self.headers {
+            if rule.path.captures(path).is_some() {
+                for (name, value) in &rule.headers {
+                    headers.append(name.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+fn parse_redirects(source: &str) -> Result<Vec<RedirectRule>, NetlifyRulesError> {
+    let mut rules = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let err = |message: String| NetlifyRulesError {
+            file: "_redirects",
+            line: i + 1,
+            message,
+        };
+
+        let mut tokens = strip_comment(line).split_whitespace();
+        let Some(from) = tokens.next() else {
+            continue;
+        };
+        let to = tokens
+            .next()
+            .ok_or_else(|| err(format!("missing target for `{from}`")))?;
+
+        let (status, force) = match tokens.next() {
+            None => (StatusCode::MOVED_PERMANENTLY, false),
+            Some(token) => {
+                let (code, force) = match token.strip_suffix('!') {
+                    Some(code) => (code, true),
+                    None => (token, false),
+                };
+                if token.contains('=') {
+                    return Err(err(format!("conditions like `{token}` are not supported")));
+                }
+                let status = code
+                    .parse::<u16>()
+                    .ok()
+                    .and_then(|code| StatusCode::from_u16(code).ok())
+                    .ok_or_else(|| err(format!("invalid status code `{token}`")))?;
+                (status, force)
+            }
+        };
+        if let Some(token) = tokens.next() {
+            return Err(err(format!("conditions like `{token}` are not supported")));
+        }
+
+        let from = PathPattern::parse(from).map_err(err)?;
+
+        let external = to.starts_with("http://") || to.starts_with("https://");
+        if status.is_redirection() {
+            if !matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+                return Err(err(format!("unsupported redirect status `{status}`")));
+            }
+            if !external && !to.starts_with('/') {
+                return Err(err(format!(
+                    "target `{to}` must start with `/` or be a URL"
+                )));
+            }
+        } else if status == StatusCode::OK || status.is_client_error() {
+            if external {
+                return Err(err(format!("proxying to `{to}` is not supported")));
+            }
+            if !to.starts_with('/') {
+                return Err(err(format!("target `{to}` must start with `/`")));
+            }
+        } else {
+            return Err(err(format!("unsupported status `{status}`")));
+        }
+
+        for placeholder in placeholders(to) {
+            let known = if placeholder == "splat" {
+                from.splat
+            } else {
+                from.segments.iter().any(
+                    |segment| matches!(segment, Segment::Placeholder(name) if name == placeholder),
+                )
+            };
+            if !known {
+                return Err(err(format!(
+                    "unknown placeholder `:{placeholder}` in `{to}`"
+                )));
+            }
+        }
+
+        rules.push(RedirectRule {
+            from,
+            to: to.to_owned(),
+            status,
+            force,
+        });
+    }
+
+    Ok(rules)
+}
+
+// Strip a comment from a line of `_redirects`. Comments start with a `#` at the start of the
+// line or after whitespace, other `#`s belong to a path, e.g. `/new#section`.
+fn strip_comment(line: &str) -> &str {
+    let mut after_whitespace = true;
+    for (i, c) in line.char_indices() {
+        if c == '#' && after_whitespace {
+            return &line[..i];
+        }
+        after_whitespace = c.is_whitespace();
+    }
+    line
+}
+
+fn parse_headers(source: &str) -> Result<Vec<HeaderRule>, NetlifyRulesError> {
+    let mut rules = Vec::<HeaderRule>::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let err = |message: String| NetlifyRulesError {
+            file: "_headers",
+            line: i + 1,
+            message,
+        };
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            rules.push(HeaderRule {
+                path: PathPattern::parse(trimmed).map_err(err)?,
+                headers: Vec::new(),
+            });
+            continue;
+        }
+
+        let rule = rules
+            .last_mut()
+            .ok_or_else(|| err(format!("header `{trimmed}` without a path")))?;
+        let (name, value) = trimmed
+            .split_once(':')
+            .ok_or_else(|| err(format!("expected `Name: value`, found `{trimmed}`")))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| err(format!("invalid header name `{}`", name.trim())))?;
+        let value = HeaderValue::from_str(value.trim())
+            .map_err(|_| err(format!("invalid value for header `{name}`")))?;
+        rule.headers.push((name, value));
+    }
+
+    Ok(rules)
+}
+
+/// A path like `/blog/:year/*`, where `:name` matches a single segment and a trailing `*`
+/// matches the rest of the path.
+#[derive(Clone, Debug)]
+struct PathPattern {
+    segments: Vec<Segment>,
+    splat: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+impl PathPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let Some(path) = pattern.strip_prefix('/') else {
+            return Err(format!("path `{pattern}` must start with `/`"));
+        };
+
+        let mut segments = Vec::new();
+        let mut splat = false;
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            if splat {
+                return Err(format!("`*` must be the last segment in `{pattern}`"));
+            }
+            if segment == "*" {
+                splat = true;
+            } else if segment.contains('*') {
+                return Err(format!("`*` must be a whole segment in `{pattern}`"));
+            } else if let Some(name) = segment.strip_prefix(':') {
+                segments.push(Segment::Placeholder(name.to_owned()));
+            } else {
+                segments.push(Segment::Literal(segment.to_owned()));
+            }
+        }
+
+        Ok(Self { segments, splat })
+    }
+
+    /// Match `path`, returning the values of the placeholders and the splat.
+    fn captures<'a>(&self, path: &'a str) -> Option<Vec<(&str, &'a str)>> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let mut rest = path.trim_end_matches('/');
+        let mut captures = Vec::new();
+
+        for segment in &self.segments {
+            if rest.is_empty() {
+                return None;
+            }
+            let (value, tail) = rest.split_once('/').unwrap_or((rest, ""));
+            match segment {
+                Segment::Literal(literal) if literal == value => (),
+                Segment::Literal(_) => return None,
+                Segment::Placeholder(name) => captures.push((name.as_str(), value)),
+            }
+            rest = tail;
+        }
+
+        if self.splat {
+            captures.push(("splat", rest));
+        } else if !rest.is_empty() {
+            return None;
+        }
+
+        Some(captures)
+    }
+}
+
+/// The names of the `:placeholder`s in `to`.
+fn placeholders(to: &str) -> impl Iterator<Item = &str> {
+    to.split(':').skip(1).filter_map(|tail| {
+        let len = tail
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(tail.len());
+        let name = &tail[..len];
+        name.starts_with(|c: char| c.is_ascii_alphabetic())
+            .then_some(name)
+    })
+}
+
+fn substitute(to: &str, captures: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(to.len());
+    let mut parts = to.split(':');
+    result.push_str(parts.next().unwrap_or_default());
+
+    for tail in parts {
+        let len = tail
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(tail.len());
+        match captures.iter().find(|(name, _)| *name == &tail[..len]) {
+            Some((_, value)) => {
+                result.extend(utf8_percent_encode(value, CAPTURE));
+                result.push_str(&tail[len..]);
+            }
+            None => {
+                result.push(':');
+                result.push_str(tail);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirects() {
+        let rules = Rules::parse(
+            Some(
+                "# comment\n\
+                 /old        /new\n\
+                 /blog/*     /news/:splat   302\n\
+                 /posts/:id  /articles/:id  308!\n\
+                 /docs       https://docs.example.com:8443/\n\
+                 /app/*      /index.html    200\n\
+                 /guide      /docs#install  302 # moved\n",
+            ),
+            None,
+        )
+        .unwrap();
+
+        let redirect = |path| rules.redirect(path);
+        assert_eq!(
+            redirect("/old/"),
+            Some(Redirect {
+                to: "/new".to_owned(),
+                status: StatusCode::MOVED_PERMANENTLY,
+                force: false,
+            })
+        );
+        assert_eq!(redirect("/blog/2024/hello").unwrap().to, "/news/2024/hello");
+        assert_eq!(redirect("/blog").unwrap().to, "/news/");
+        let post = redirect("/posts/42").unwrap();
+        assert_eq!(post.to, "/articles/42");
+        assert!(post.force);
+        assert_eq!(
+            redirect("/docs").unwrap().to,
+            "https://docs.example.com:8443/"
+        );
+        assert!(redirect("/app/settings").unwrap().is_rewrite());
+        assert_eq!(
+            redirect("/guide"),
+            Some(Redirect {
+                to: "/docs#install".to_owned(),
+                status: StatusCode::FOUND,
+                force: false,
+            })
+        );
+        assert_eq!(
+            redirect("/posts/a?b#c%d").unwrap().to,
+            "/articles/a%3Fb%23c%25d"
+        );
+        assert_eq!(redirect("/blog/a?b/c").unwrap().to, "/news/a%3Fb/c");
+        assert_eq!(redirect("/posts/42/comments"), None);
+        assert_eq!(redirect("/other"), None);
+    }
+
+    #[test]
+    fn redirect_errors() {
+        for (source, message) in [
+            ("/a", "_redirects:1: missing target for `/a`"),
+            ("\n/a /b abc", "_redirects:2: invalid status code `abc`"),
+            (
+                "/a /b 302 Country=us",
+                "_redirects:1: conditions like `Country=us` are not supported",
+            ),
+            (
+                "/a /b/:id",
+                "_redirects:1: unknown placeholder `:id` in `/b/:id`",
+            ),
+            (
+                "/a/* https://example.com 200",
+                "_redirects:1: proxying to `https://example.com` is not supported",
+            ),
+            (
+                "/a/*/b /c",
+                "_redirects:1: `*` must be the last segment in `/a/*/b`",
+            ),
+            ("a /b", "_redirects:1: path `a` must start with `/`"),
+        ] {
+            let err = Rules::parse(Some(source), None).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn headers() {
+        let rules = Rules::parse(
+            None,
+            Some(
+                "/*\n  X-Frame-Options: DENY\n\n\
+                 /assets/*\n  Cache-Control: max-age=31536000\n  X-Robots-Tag: noindex\n",
+            ),
+        )
+        .unwrap();
+
+        let mut headers = HeaderMap::new();
+        rules.apply_headers("/assets/app.js", &mut headers);
+        assert_eq!(headers["x-frame-options"], "DENY");
+        assert_eq!(headers["cache-control"], "max-age=31536000");
+        assert_eq!(headers["x-robots-tag"], "noindex");
+
+        let mut headers = HeaderMap::new();
+        rules.apply_headers("/index.html", &mut headers);
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn header_errors() {
+        for (source, message) in [
+            (
+                "  X-Frame-Options: DENY",
+                "_headers:1: header `X-Frame-Options: DENY` without a path",
+            ),
+            (
+                "/*\n  X-Frame-Options DENY",
+                "_headers:2: expected `Name: value`, found `X-Frame-Options DENY`",
+            ),
+            (
+                "/*\n  X Frame: DENY",
+                "_headers:2: invalid header name `X Frame`",
+            ),
+        ] {
+            let err = Rules::parse(None, Some(source)).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{html, integrity::base64};
+use bytes::{BufMut, Bytes, BytesMut};
+use http::HeaderValue;
+use std::ops::Range;
+
+/// The placeholder for the nonce in a policy.
+const PLACEHOLDER: &str = "{nonce}";
+
+/// A `Content-Security-Policy` with a fresh nonce for every HTML response.
+#[derive(Debug)]
+pub(crate) struct CspNonce {
+    policy: String,
+}
+
+impl CspNonce {
+    /// A policy with `{nonce}` placeholders, e.g. `script-src 'nonce-{nonce}'`.
+    pub(crate) fn new(policy: &str) -> Self {
+        HeaderValue::from_str(&policy.replace(PLACEHOLDER, ""))
+            .expect("policy isn't a valid header value");
+        Self {
+            policy: policy.to_owned(),
+        }
+    }
+
+    /// The policy for a response with `nonce`.
+    pub(crate) fn policy(&self, nonce: &str) -> HeaderValue {
+        HeaderValue::from_str(&self.policy.replace(PLACEHOLDER, nonce)).expect("valid policy")
+    }
+}
+
+/// The length of a nonce, 128 bits encoded as base64.
+const LEN: usize = 24;
+
+/// A random nonce, 128 bits encoded as base64.
+pub(crate) fn generate() -> String {
+    let mut bytes = [0; 16];
+    getrandom::getrandom(&mut bytes).expect("random nonce");
+    base64(&bytes)
+}
+
+/// Where the nonce of a `<script>` or `<style>` tag goes.
+#[derive(Clone, Debug)]
+pub(crate) enum Point {
+    /// A `nonce` attribute is inserted here, right after the name of the tag.
+    Insert(usize),
+    /// The value of the `nonce` the tag already has is replaced, since a nonce that is the same
+    /// for every response doesn't protect anything.
+    Replace(Range<usize>),
+}
+
+/// The points in `html` where the nonce goes.
+pub(crate) fn insertion_points(html: &str) -> Vec<Point> {
+    html::start_tags(html)
+        .filter(|tag| tag.is("script") || tag.is("style"))
+        .map(|tag| {
+            let value = tag
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name.eq_ignore_ascii_case("nonce"))
+                .and_then(|attribute| attribute.value.as_ref());
+            match value {
+                Some((_, range)) => Point::Replace(range.clone()),
+                None => Point::Insert(tag.range.start + 1 + tag.name.len()),
+            }
+        })
+        .collect()
+}
+
+/// The length of a document of `len` bytes with a nonce spliced in at each of `points`.
+pub(crate) fn spliced_len(len: usize, points: &[Point]) -> usize {
+    points.iter().fold(len, |len, point| match point {
+        Point::Insert(_) => len + " nonce=\"\"".len() + LEN,
+        Point::Replace(range) => len - range.len() + LEN,
+    })
+}
+
+/// `html` with the nonce at each of `points`.
+pub(crate) fn splice(html: &Bytes, points: &[Point], nonce: &str) -> Bytes {
+    let attribute = format!(" nonce=\"{nonce}\"");
+    let mut spliced = BytesMut::with_capacity(html.len() + points.len() * attribute.len());
+    let mut last = 0;
+    for point in points {
+        match point {
+            Point::Insert(at) => {
+                spliced.put_slice(&html[last..*at]);
+                spliced.put_slice(attribute.as_bytes());
+                last = *at;
+            }
+            Point::Replace(range) => {
+                spliced.put_slice(&html[last..range.start]);
+                spliced.put_slice(nonce.as_bytes());
+                last = range.end;
+            }
+        }
+    }
+    spliced.put_slice(&html[last..]);
+    spliced.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splice_nonce() {
+        let html = r#"<title>a</title><SCRIPT>let a = "<style>"</SCRIPT>
+<script nonce="fixed" src="app.js"></script><style nonce>p { color: red }</style>"#;
+
+        let points = insertion_points(html);
+        let spliced = splice(
+            &Bytes::from_static(html.as_bytes()),
+            &points,
+            &"A".repeat(LEN),
+        );
+        assert_eq!(spliced.len(), spliced_len(html.len(), &points));
+        assert_eq!(
+            splice(&Bytes::from_static(html.as_bytes()), &points, "abc="),
+            r#"<title>a</title><SCRIPT nonce="abc=">let a = "<style>"</SCRIPT>
+<script nonce="abc=" src="app.js"></script><style nonce="abc=" nonce>p { color: red }</style>"#
+        );
+    }
+
+    #[test]
+    fn fresh_nonces() {
+        let nonce = generate();
+        assert_eq!(nonce.len(), LEN);
+        assert_ne!(nonce, generate());
+
+        let csp = CspNonce::new("script-src 'nonce-{nonce}'; style-src 'nonce-{nonce}'");
+        assert_eq!(
+            csp.policy(&nonce),
+            format!("script-src 'nonce-{nonce}'; style-src 'nonce-{nonce}'").as_str()
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use include_dir::Dir;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Decides which embedded files are visible to clients.
+///
+/// Rules are evaluated in order and the last matching rule wins. Dotfiles are hidden unless a
+/// later allow rule matches them.
+#[derive(Clone, Debug)]
+pub(crate) struct Policy {
+    hide_dotfiles: bool,
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    pattern: Pattern,
+    allow: bool,
+}
+
+/// A glob pattern matched against a path and its parent directories.
+#[derive(Clone, Debug)]
+pub(crate) struct Pattern(tower_serve_static_glob::Pattern);
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            hide_dotfiles: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Policy {
+    pub(crate) fn hide_dotfiles(&mut self, hide: bool) {
+        self.hide_dotfiles = hide;
+    }
+
+    pub(crate) fn push(&mut self, pattern: &str, allow: bool) {
+        self.rules.push(Rule {
+            pattern: Pattern::new(pattern),
+            allow,
+        });
+    }
+
+    /// Check whether the file at `path` may be served.
+    pub(crate) fn is_visible(&self, path: &Path) -> bool {
+        let segments = path
+            .iter()
+            .map(|segment| segment.to_string_lossy())
+            .collect::<Vec<_>>();
+
+        let mut visible =
+            !(self.hide_dotfiles && segments.iter().any(|segment| segment.starts_with('.')));
+
+        for rule in &self.rules {
+            if rule.allow != visible && rule.pattern.0.matches_segments(&segments) {
+                visible = rule.allow;
+            }
+        }
+
+        visible
+    }
+
+    /// The directories of `layers` that contain a visible file, directly or in a subdirectory.
+    /// Directories without visible files are treated as nonexistent.
+    pub(crate) fn visible_dirs(&self, layers: &[&Dir<'_>]) -> HashSet<PathBuf> {
+        fn visit(policy: &Policy, dir: &Dir<'_>, visible: &mut HashSet<PathBuf>) {
+            for file in dir.files() {
+                if policy.is_visible(file.path()) {
+                    for parent in file.path().ancestors().skip(1) {
+                        if parent.as_os_str().is_empty() || !visible.insert(parent.to_path_buf()) {
+                            break;
+                        }
+                    }
+                }
+            }
+            for dir in dir.dirs() {
+                visit(policy, dir, visible);
+            }
+        }
+
+        let mut visible = HashSet::new();
+        for layer in layers {
+            visit(self, layer, &mut visible);
+        }
+        visible
+    }
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        Self(tower_serve_static_glob::Pattern::new(pattern))
+    }
+
+    /// Check whether the pattern matches `path` or any of its parent directories.
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let segments = path
+            .iter()
+            .map(|segment| segment.to_string_lossy())
+            .collect::<Vec<_>>();
+        self.0.matches_segments(&segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let mut policy = Policy::default();
+        policy.push("**/*.map", false);
+        policy.push("debug/app.js.map", true);
+
+        assert!(!policy.is_visible(Path::new("js/app.js.map")));
+        assert!(policy.is_visible(Path::new("debug/app.js.map")));
+        assert!(policy.is_visible(Path::new("js/app.js")));
+    }
+
+    #[test]
+    fn unanchored_patterns_match_any_segment() {
+        let mut policy = Policy::default();
+        policy.push("node_modules", false);
+
+        assert!(!policy.is_visible(Path::new("node_modules/a/index.js")));
+        assert!(!policy.is_visible(Path::new("vendor/node_modules/index.js")));
+        assert!(policy.is_visible(Path::new("vendor/index.js")));
+    }
+
+    #[test]
+    fn leading_slash_anchors_patterns() {
+        let mut policy = Policy::default();
+        policy.push("/_redirects", false);
+
+        assert!(!policy.is_visible(Path::new("_redirects")));
+        assert!(policy.is_visible(Path::new("docs/_redirects")));
+    }
+
+    #[test]
+    fn dotfiles() {
+        let mut policy = Policy::default();
+        assert!(!policy.is_visible(Path::new(".env")));
+        assert!(!policy.is_visible(Path::new(".git/config")));
+
+        policy.push(".well-known/**", true);
+        assert!(policy.is_visible(Path::new(".well-known/security.txt")));
+        assert!(!policy.is_visible(Path::new(".env")));
+
+        policy.hide_dotfiles(false);
+        assert!(policy.is_visible(Path::new(".env")));
+    }
+}
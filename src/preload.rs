@@ -0,0 +1,137 @@
+use crate::html::{self, Tag};
+use http::HeaderValue;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+// Characters that aren't allowed in the URI reference of a `Link` header.
+const LINK: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>');
+
+/// A `Link` header value with preload hints for the scripts, stylesheets and preloaded
+/// resources, e.g. fonts, that `html` references from the same origin.
+///
+/// Returns `None` if the document doesn't reference any.
+pub(crate) fn links(html: &str) -> Option<HeaderValue> {
+    let mut links = Vec::<(String, String)>::new();
+    for tag in html::start_tags(html) {
+        let Some((url, params)) = hint(&tag) else {
+            continue;
+        };
+        if is_same_origin(url) && !links.iter().any(|(existing, _)| existing == url) {
+            links.push((url.to_owned(), params));
+        }
+    }
+
+    let value = links
+        .iter()
+        .map(|(url, params)| {
+            let url = utf8_percent_encode(&url.replace("&amp;", "&"), LINK).to_string();
+            format!("<{url}>; {params}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    (!value.is_empty()).then(|| HeaderValue::from_str(&value).expect("encoded link"))
+}
+
+// The URL and the parameters of the preload hint for `tag`.
+fn hint<'a>(tag: &Tag<'a>) -> Option<(&'a str, String)> {
+    let crossorigin = tag.attribute("crossorigin");
+    let (url, mut params) = if tag.is("script") {
+        let url = tag.attribute("src")?;
+        match tag.attribute("type").map(str::trim) {
+            Some(kind) if kind.eq_ignore_ascii_case("module") => {
+                (url, "rel=modulepreload".to_owned())
+            }
+            None | Some("") => (url, "rel=preload; as=script".to_owned()),
+            Some(kind) if kind.to_ascii_lowercase().ends_with("javascript") => {
+                (url, "rel=preload; as=script".to_owned())
+            }
+            // e.g. JSON data or templates
+            Some(_) => return None,
+        }
+    } else if tag.is("link") {
+        let url = tag.attribute("href")?;
+        let rel = tag.attribute("rel")?.to_ascii_lowercase();
+        let rel = rel.split_ascii_whitespace().collect::<Vec<_>>();
+        if rel.contains(&"stylesheet") && !rel.contains(&"alternate") {
+            (url, "rel=preload; as=style".to_owned())
+        } else if rel.contains(&"modulepreload") {
+            (url, "rel=modulepreload".to_owned())
+        } else if rel.contains(&"preload") {
+            let kind = tag.attribute("as")?.trim().to_ascii_lowercase();
+            if kind.is_empty() || !kind.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return None;
+            }
+            let mut params = format!("rel=preload; as={kind}");
+            if let Some(mime) = tag.attribute("type").filter(|mime| is_token(mime)) {
+                params.push_str(&format!("; type=\"{mime}\""));
+            }
+            // Fonts are always fetched in CORS mode.
+            if kind == "font" && crossorigin.is_none() {
+                params.push_str("; crossorigin");
+            }
+            (url, params)
+        } else {
+            return None;
+        }
+    } else {
+        return None;
+    };
+
+    match crossorigin.map(str::trim) {
+        Some(value) if value.eq_ignore_ascii_case("use-credentials") => {
+            params.push_str("; crossorigin=use-credentials")
+        }
+        Some(_) => params.push_str("; crossorigin"),
+        None => {}
+    }
+
+    Some((url.trim(), params))
+}
+
+fn is_same_origin(url: &str) -> bool {
+    let has_scheme = url
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.contains('/'));
+    !url.is_empty() && !url.starts_with("//") && !url.starts_with('#') && !has_scheme
+}
+
+// Whether `value` can be written in a quoted parameter as is.
+fn is_token(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b.is_ascii_graphic() && b != b'"' && b != b'\\')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints() {
+        let html = r#"<!DOCTYPE html>
+<link rel="stylesheet" href="/style.css">
+<link rel="alternate stylesheet" href="/dark.css">
+<link rel="preload" href="/fonts/inter.woff2" as="font" type="font/woff2">
+<link rel="icon" href="/favicon.ico">
+<link rel="stylesheet" href="https://cdn.example.com/reset.css">
+<script type="module" src="app.js" crossorigin="use-credentials"></script>
+<script src="/legacy.js?v=1&amp;x=2"></script>
+<script src="/style.css"></script>
+<script type="application/json" src="/data.json"></script>
+<script src="//cdn.example.com/analytics.js"></script>
+<!-- <script src="/commented.js"></script> -->"#;
+
+        assert_eq!(
+            links(html).unwrap(),
+            "</style.css>; rel=preload; as=style, \
+             </fonts/inter.woff2>; rel=preload; as=font; type=\"font/woff2\"; crossorigin, \
+             <app.js>; rel=modulepreload; crossorigin=use-credentials, \
+             </legacy.js?v=1&x=2>; rel=preload; as=script"
+        );
+
+        assert!(links("<p>Hello</p>").is_none());
+        assert_eq!(
+            links(r#"<script src="/a b/ü.js"></script>"#).unwrap(),
+            "</a%20b/%C3%BC.js>; rel=preload; as=script"
+        );
+    }
+}
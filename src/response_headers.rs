@@ -0,0 +1,65 @@
+use http::{request::Parts, HeaderMap, HeaderValue};
+use std::{fmt, path::Path, sync::Arc};
+
+/// The file a response is about to be sent for, passed to the callbacks of
+/// [`ServeDir::response_headers`](crate::ServeDir::response_headers) and
+/// [`ServeFile::response_headers`](crate::ServeFile::response_headers).
+#[derive(Clone, Copy, Debug)]
+pub struct ResolvedFile<'a> {
+    pub(crate) path: Option<&'static Path>,
+    pub(crate) mime: &'a HeaderValue,
+    pub(crate) contents: &'static [u8],
+    #[cfg(feature = "metadata")]
+    pub(crate) metadata: Option<&'a include_dir::Metadata>,
+}
+
+impl ResolvedFile<'_> {
+    /// The path of the file relative to the embedded directory, after rewrites, index files
+    /// and negotiation.
+    ///
+    /// `None` for [`ServeFile`](crate::ServeFile), which serves a single file.
+    pub fn path(&self) -> Option<&'static Path> {
+        self.path
+    }
+
+    /// The `Content-Type` of the response.
+    pub fn mime(&self) -> &HeaderValue {
+        self.mime
+    }
+
+    /// The contents of the file.
+    pub fn contents(&self) -> &'static [u8] {
+        self.contents
+    }
+
+    /// The metadata of the file, if it was embedded with metadata.
+    #[cfg(feature = "metadata")]
+    pub fn metadata(&self) -> Option<&include_dir::Metadata> {
+        self.metadata
+    }
+}
+
+type Callback = dyn Fn(&ResolvedFile<'_>, &Parts, &mut HeaderMap) + Send + Sync;
+
+/// A user supplied callback customizing the headers of file responses.
+#[derive(Clone)]
+pub(crate) struct ResponseHeaders(Arc<Callback>);
+
+impl ResponseHeaders {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&ResolvedFile<'_>, &Parts, &mut HeaderMap) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn apply(&self, file: &ResolvedFile<'_>, parts: &Parts, headers: &mut HeaderMap) {
+        (self.0)(file, parts, headers)
+    }
+}
+
+impl fmt::Debug for ResponseHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResponseHeaders(..)")
+    }
+}
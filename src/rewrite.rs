@@ -0,0 +1,90 @@
+use std::{fmt, sync::Arc};
+
+type RewriteFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// A rewrite of the request path, added with [`ServeDir::rewrite`](crate::ServeDir::rewrite) or
+/// [`ServeDir::rewrite_regex`](crate::ServeDir::rewrite_regex).
+#[derive(Clone)]
+pub(crate) enum Rewrite {
+    Fn(Arc<RewriteFn>),
+    #[cfg(feature = "regex")]
+    Regex {
+        regex: regex::Regex,
+        replacement: String,
+    },
+}
+
+impl Rewrite {
+    /// The rewritten path, or `None` to keep `path` as is.
+    pub(crate) fn apply(&self, path: &str) -> Option<String> {
+        match self {
+            Rewrite::Fn(f) => f(path),
+            #[cfg(feature = "regex")]
+            Rewrite::Regex { regex, replacement } => regex
+                .is_match(path)
+                .then(|| regex.replace(path, replacement.as_str()).into_owned()),
+        }
+    }
+}
+
+impl fmt::Debug for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rewrite::Fn(_) => f.write_str("Fn(..)"),
+            #[cfg(feature = "regex")]
+            Rewrite::Regex { regex, replacement } => f
+                .debug_struct("Regex")
+                .field("regex", &regex.as_str())
+                .field("replacement", replacement)
+                .finish(),
+        }
+    }
+}
+
+/// Apply all `rewrites` in order, each one seeing the result of the previous ones.
+pub(crate) fn apply_all(rewrites: &[Rewrite], path: String) -> String {
+    rewrites
+        .iter()
+        .fold(path, |path, rewrite| rewrite.apply(&path).unwrap_or(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closures() {
+        let rewrites = [
+            Rewrite::Fn(Arc::new(|path| {
+                path.strip_prefix("/v2").map(|rest| rest.to_owned())
+            })),
+            Rewrite::Fn(Arc::new(|path| {
+                (path == "/latest.js").then(|| "/app.js".to_owned())
+            })),
+        ];
+
+        assert_eq!(apply_all(&rewrites, "/v2/latest.js".to_owned()), "/app.js");
+        assert_eq!(
+            apply_all(&rewrites, "/v1/latest.js".to_owned()),
+            "/v1/latest.js"
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex() {
+        let rewrites = [Rewrite::Regex {
+            regex: regex::Regex::new(r"^/v\d+/assets/(?<rest>.*)$").unwrap(),
+            replacement: "/assets/$rest".to_owned(),
+        }];
+
+        assert_eq!(
+            apply_all(&rewrites, "/v12/assets/css/app.css".to_owned()),
+            "/assets/css/app.css"
+        );
+        assert_eq!(
+            apply_all(&rewrites, "/assets/app.css".to_owned()),
+            "/assets/app.css"
+        );
+    }
+}
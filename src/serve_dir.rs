@@ -1,44 +1,169 @@
-use super::{AsyncReadBody, DEFAULT_CAPACITY};
+#[cfg(feature = "csp-nonce")]
+use super::nonce::{self, CspNonce};
+#[cfg(feature = "vite")]
+use super::vite::{ViteManifest, ViteManifestError};
+use super::{
+    app_config::AppConfig,
+    base_path::BasePath,
+    cache::ContentCache,
+    cors::{self, Cors},
+    disposition,
+    fingerprint::{self, Fingerprints},
+    image::ImageFormat,
+    include::{IncludeError, Includes},
+    integrity::{Digests, Integrity},
+    language::{self, LanguageNegotiation},
+    netlify::{NetlifyRulesError, Rules},
+    policy::{Pattern, Policy},
+    preload,
+    response_headers::{ResolvedFile, ResponseHeaders},
+    rewrite::{self, Rewrite},
+    security::{self, SecurityHeaders},
+    served_asset::{fnv1a, ServeOutcome, ServedAsset},
+    AsyncReadBody, DEFAULT_CAPACITY,
+};
 use bytes::Bytes;
-use http::{header, HeaderValue, Request, Response, StatusCode, Uri};
+use http::{header, request::Parts, HeaderMap, HeaderValue, Request, Response, StatusCode, Uri};
 use http_body::Frame;
 use http_body_util::{combinators::BoxBody, BodyExt, Empty};
 use include_dir::{Dir, File};
-use percent_encoding::percent_decode;
+use percent_encoding::{percent_decode, utf8_percent_encode, AsciiSet, CONTROLS};
+use sha2::{Digest, Sha256};
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     convert::Infallible,
     future::Future,
     io,
     path::{Path, PathBuf},
     pin::Pin,
+    sync::{Arc, OnceLock},
     task::{Context, Poll},
 };
 use tower_service::Service;
 
 /// Service that serves files from a given directory and all its sub directories.
 ///
-/// The `Content-Type` will be guessed from the file extension.
+/// Additional directories can be layered on top with [`ServeDir::overlay`].
+///
+/// The `Content-Type` will be guessed from the file extension. With the `sniff` feature enabled,
+/// files with an unknown extension are identified by their leading magic bytes instead.
 ///
 /// An empty response with status `404 Not Found` will be returned if:
 ///
 /// - The file doesn't exist
+/// - The file is hidden, see [`ServeDir::hide_dotfiles`], [`ServeDir::allow`] and [`ServeDir::deny`]
 /// - Any segment of the path contains `..`
 /// - Any segment of the path contains a backslash
 #[derive(Clone, Debug)]
 pub struct ServeDir {
-    dir: &'static Dir<'static>,
+    // Ordered from the base layer to the topmost overlay.
+    layers: Vec<&'static Dir<'static>>,
     append_index_html_on_directories: bool,
     buf_chunk_size: usize,
+    policy: Policy,
+    // Built from `layers`, `policy` and `language` on first use.
+    index: Arc<OnceLock<Index>>,
+    language: Option<LanguageNegotiation>,
+    negotiate_image_formats: bool,
+    netlify_rules: Option<Rules>,
+    rewrites: Vec<Rewrite>,
+    response_headers: Option<ResponseHeaders>,
+    attachments: Vec<Pattern>,
+    security_headers: Option<SecurityHeaders>,
+    cross_origin_isolation: bool,
+    cors: Vec<(Pattern, Cors)>,
+    digests: Vec<&'static Digests>,
+    digest_headers: bool,
+    fingerprints: Option<Arc<Fingerprints>>,
+    redirect_stale_fingerprints: bool,
+    preload_links: Option<Arc<ContentCache<Option<HeaderValue>>>>,
+    base_path: Option<Arc<BasePath>>,
+    includes: Option<Arc<Includes>>,
+    app_config: Option<Arc<AppConfig>>,
+    #[cfg(feature = "csp-nonce")]
+    csp_nonce: Option<Arc<CspNonce>>,
+    // HTML documents and stylesheets after `includes`, `base_path`, `app_config` and
+    // `csp_nonce`.
+    transformed: Arc<ContentCache<Option<Transformed>>>,
+}
+
+// Lookups that would otherwise walk the layers on every request.
+#[derive(Debug, Default)]
+struct Index {
+    // The directories with a visible file.
+    visible_dirs: HashSet<PathBuf>,
+    // The visible language variants of each file and their tags, e.g. `index.de.html` for
+    // `index.html`. Empty without `ServeDir::negotiate_language`.
+    variants: HashMap<PathBuf, Vec<(String, PathBuf)>>,
+}
+
+// An embedded file that is changed before it is served.
+#[derive(Clone, Debug)]
+struct Transformed {
+    contents: Bytes,
+    // Where a nonce is inserted for every response, see `ServeDir::csp_nonce`.
+    #[cfg(feature = "csp-nonce")]
+    nonces: Option<Arc<[nonce::Point]>>,
+    // The length of the body, including the nonces.
+    content_length: HeaderValue,
+    // `None` if the body changes with every response.
+    etag: Option<HeaderValue>,
 }
 
 impl ServeDir {
     /// Create a new [`ServeDir`].
     pub fn new(dir: &'static Dir<'static>) -> Self {
         Self {
-            dir,
+            layers: vec![dir],
             append_index_html_on_directories: true,
             buf_chunk_size: DEFAULT_CAPACITY,
+            policy: Policy::default(),
+            index: Arc::default(),
+            language: None,
+            negotiate_image_formats: false,
+            netlify_rules: None,
+            rewrites: Vec::new(),
+            response_headers: None,
+            attachments: Vec::new(),
+            security_headers: None,
+            cross_origin_isolation: false,
+            cors: Vec::new(),
+            digests: Vec::new(),
+            digest_headers: false,
+            fingerprints: None,
+            redirect_stale_fingerprints: false,
+            preload_links: None,
+            base_path: None,
+            includes: None,
+            app_config: None,
+            #[cfg(feature = "csp-nonce")]
+            csp_nonce: None,
+            transformed: Arc::default(),
+        }
+    }
+
+    /// Layer another directory on top of the current ones.
+    ///
+    /// Lookups check the topmost layer first and fall through to the layers below if the file
+    /// doesn't exist there, so overlays can replace individual files (e.g. a tenant specific
+    /// theme on top of a base theme). A directory exists if it exists in any layer, and its
+    /// `index.html` is resolved across all layers as well.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if [`ServeDir::with_includes`] was called before and the includes can't be
+    /// expanded with the overlay, e.g. because it adds a document that includes a missing file.
+    pub fn overlay(mut self, dir: &'static Dir<'static>) -> Self {
+        self.layers.push(dir);
+        self.index = Arc::default();
+        if self.includes.is_some() {
+            let includes = Includes::expand(&self.layers)
+                .unwrap_or_else(|err| panic!("overlay breaks the includes: {err}"));
+            self.includes = Some(Arc::new(includes));
+            self.reset_transformed();
         }
+        self
     }
 
     /// If the requested path is a directory append `index.html`.
@@ -58,238 +183,1333 @@ impl ServeDir {
         self.buf_chunk_size = chunk_size;
         self
     }
-}
-
-impl<ReqBody> Service<Request<ReqBody>> for ServeDir {
-    type Response = Response<ResponseBody>;
-    type Error = Infallible;
-    type Future = ResponseFuture;
 
-    #[inline]
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        Poll::Ready(Ok(()))
+    /// Hide files and directories whose name starts with a `.`, such as `.env` or `.git/`.
+    ///
+    /// Hidden files are treated as nonexistent, use [`ServeDir::allow`] to expose
+    /// specific ones (e.g. `.well-known/**`).
+    ///
+    /// Defaults to `true`.
+    pub fn hide_dotfiles(mut self, hide: bool) -> Self {
+        self.policy.hide_dotfiles(hide);
+        self.index = Arc::default();
+        self
     }
 
-    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        // build and validate the path
-        let path = req.uri().path();
-        let path = path.trim_start_matches('/');
+    /// Serve files matching the glob `pattern`, even if they are dotfiles or were denied before.
+    ///
+    /// Patterns are matched against the path relative to the embedded directory. Patterns
+    /// without a `/` are matched against every file and directory name instead, so `*.map`
+    /// matches `js/app.js.map`, while `/secret` only matches at the root. Rules are evaluated in order and the last matching rule wins.
+    ///
+    /// Supports `?`, `*`, `**`, `[a-z]` and `{a,b}`.
+    pub fn allow(mut self, pattern: &str) -> Self {
+        self.policy.push(pattern, true);
+        self.index = Arc::default();
+        self
+    }
 
-        let path_decoded = if let Ok(decoded_utf8) = percent_decode(path.as_ref()).decode_utf8() {
-            decoded_utf8
-        } else {
-            return ResponseFuture {
-                inner: Some(Inner::Invalid),
-            };
-        };
+    /// Treat files matching the glob `pattern` as nonexistent.
+    ///
+    /// See [`ServeDir::allow`] for the pattern syntax. Denied files are never served, neither
+    /// directly nor as an `index.html`, and directories without any visible file are reported
+    /// as `404 Not Found` instead of being redirected to.
+    pub fn deny(mut self, pattern: &str) -> Self {
+        self.policy.push(pattern, false);
+        self.index = Arc::default();
+        self
+    }
 
-        let mut full_path = PathBuf::new();
-        for seg in path_decoded.split('/') {
-            if seg.starts_with("..") || seg.contains('\\') {
-                return ResponseFuture {
-                    inner: Some(Inner::Invalid),
-                };
-            }
-            full_path.push(seg);
-        }
+    /// Serve language variants of the requested file based on the request's preferred language.
+    ///
+    /// For a request to `/index.html` this serves e.g. `index.de.html` if German is preferred.
+    /// Negotiated responses include the `Vary` header and the `Content-Language` header of the
+    /// chosen variant. See [`LanguageNegotiation`] for how the variant is picked.
+    pub fn negotiate_language(mut self, negotiation: LanguageNegotiation) -> Self {
+        self.language = Some(negotiation);
+        self.index = Arc::default();
+        self
+    }
 
-        if !req.uri().path().ends_with('/') {
-            if is_dir(self.dir, &full_path) {
-                let location =
-                    HeaderValue::from_str(&append_slash_on_path(req.uri().clone()).to_string())
-                        .unwrap();
-                return ResponseFuture {
-                    inner: Some(Inner::Redirect(location)),
-                };
-            }
-        } else if is_dir(self.dir, &full_path) {
-            if self.append_index_html_on_directories {
-                full_path.push("index.html");
-            } else {
-                return ResponseFuture {
-                    inner: Some(Inner::NotFound),
-                };
-            }
-        }
+    /// Serve `avif` or `webp` siblings of `png`, `jpeg` and `gif` images to clients supporting them.
+    ///
+    /// For a request to `/hero.png` this serves `hero.avif` or `hero.webp` if it exists and the
+    /// client explicitly lists the format in its `Accept` header, preferring the one with the
+    /// higher q-value and `avif` on a tie. The relative `Content-Location` header points to the
+    /// served file and `Vary: Accept` is added whenever a sibling exists. Falls back to the original.
+    ///
+    /// Defaults to `false`.
+    pub fn negotiate_image_formats(mut self, negotiate: bool) -> Self {
+        self.negotiate_image_formats = negotiate;
+        self
+    }
 
-        let file = if let Some(file) = self.dir.get_file(&full_path) {
-            file
-        } else {
-            return ResponseFuture {
-                inner: Some(Inner::NotFound),
-            };
+    /// Apply the Netlify style `_redirects` and `_headers` files in the root of the directory.
+    ///
+    /// `_redirects` lists one rule per line, `from to [status][!]`. The status defaults to `301`.
+    /// `:name` segments and a trailing `*` in `from` are substituted for `:name` and `:splat` in
+    /// `to`. Redirects keep the query string of the request. A status of `200` serves `to`
+    /// instead of the requested file, and `404` or other `4xx` statuses serve `to` with that
+    /// status, e.g. for a custom error page. The first matching rule wins, and it is only applied
+    /// if the requested file doesn't exist, unless the status is followed by `!`. A `#` at the
+    /// start of a line or after whitespace starts a comment, so targets can have fragments.
+    ///
+    /// `_headers` lists paths with the same syntax as `from`, each followed by indented
+    /// `Name: value` lines. The headers of all matching paths are added to served files.
+    ///
+    /// The files are read once, here, and are hidden from clients. Conditions (e.g.
+    /// `Country=de`), proxying to other hosts and anything else that can't be applied returns an
+    /// error pointing to the offending line instead of being ignored.
+    pub fn with_netlify_rules(mut self) -> Result<Self, NetlifyRulesError> {
+        let read = |name: &'static str| {
+            self.layers
+                .iter()
+                .rev()
+                .find_map(|layer| layer.get_file(name))
+                .map(|file| {
+                    file.contents_utf8()
+                        .ok_or_else(|| NetlifyRulesError::not_utf8(name))
+                })
+                .transpose()
         };
+        let rules = Rules::parse(read("_redirects")?, read("_headers")?)?;
 
-        #[cfg(feature = "metadata")]
-        if super::unmodified_since_request_condition(file, &req) {
-            return ResponseFuture {
-                inner: Some(Inner::NotModified),
-            };
-        }
+        self.netlify_rules = Some(rules);
+        self.policy.push("/_redirects", false);
+        self.policy.push("/_headers", false);
+        self.index = Arc::default();
+        Ok(self)
+    }
 
-        let guess = mime_guess::from_path(&full_path);
-        let mime = guess
-            .first_raw()
-            .map(HeaderValue::from_static)
-            .unwrap_or_else(|| {
-                HeaderValue::from_str(mime::APPLICATION_OCTET_STREAM.as_ref()).unwrap()
-            });
+    /// Read the manifest of a Vite build from the embedded directory, to resolve entry points
+    /// into the tags that load them.
+    ///
+    /// The manifest is read from `.vite/manifest.json`, or `manifest.json` for Vite 4 and
+    /// earlier, across all layers. It doesn't need to be served, so it can be hidden. If digests
+    /// were added with [`ServeDir::with_digests`], the tags include the SHA-384 Subresource
+    /// Integrity of each file.
+    ///
+    /// ```
+    /// # use include_dir::Dir;
+    /// # use tower_serve_static::{include_assets, include_digests, Digests, ServeDir};
+    /// static DIST: Dir<'static> = include_assets!("$CARGO_MANIFEST_DIR/tests/vite");
+    /// static DIGESTS: Digests = include_digests!("$CARGO_MANIFEST_DIR/tests/vite");
+    ///
+    /// let service = ServeDir::new(&DIST).with_digests(&DIGESTS);
+    /// let manifest = service.vite_manifest()?;
+    ///
+    /// // `<link rel="stylesheet" …>`, `<link rel="modulepreload" …>` and `<script type="module" …>`
+    /// let tags = manifest.entry("src/main.ts").unwrap().to_string();
+    /// # Ok::<(), tower_serve_static::ViteManifestError>(())
+    /// ```
+    #[cfg(feature = "vite")]
+    pub fn vite_manifest(&self) -> Result<ViteManifest, ViteManifestError> {
+        let file = [".vite/manifest.json", "manifest.json"]
+            .into_iter()
+            .find_map(|name| {
+                self.layers
+                    .iter()
+                    .rev()
+                    .find_map(|layer| layer.get_file(name))
+            })
+            .ok_or_else(|| ViteManifestError::new("no `.vite/manifest.json` found"))?;
+        let json = file
+            .contents_utf8()
+            .ok_or_else(|| ViteManifestError::new("file is not valid UTF-8"))?;
+
+        ViteManifest::parse(json, |file| {
+            self.integrity(file).map(|integrity| integrity.sha384())
+        })
+    }
 
-        ResponseFuture {
-            inner: Some(Inner::File(file, mime, self.buf_chunk_size)),
-        }
+    /// Rewrite the request path before looking up the file.
+    ///
+    /// The closure receives the percent-decoded path, including the leading `/`, and returns
+    /// the new path or `None` to keep it. Rewrites run in the order they were added, each one
+    /// receiving the result of the previous ones, before anything else looks at the path. The
+    /// result is validated like the request path, so it can't escape the directory.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// // Serve `/v2/assets/app.js` from `assets/app.js`.
+    /// let service = ServeDir::new(&ASSETS_DIR).rewrite(|path| {
+    ///     path.strip_prefix("/v2").map(str::to_owned)
+    /// });
+    /// ```
+    ///
+    /// The file that was served is available as the [`ResolvedPath`] response extension.
+    pub fn rewrite<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.rewrites.push(Rewrite::Fn(Arc::new(f)));
+        self
     }
-}
 
-fn is_dir(dir: &Dir<'static>, path: &Path) -> bool {
-    if path.as_os_str() == std::ffi::OsStr::new("") {
-        return true;
+    /// Rewrite request paths matching `regex`, see [`ServeDir::rewrite`].
+    ///
+    /// The match is replaced with `replacement`, which can refer to capture groups as `$1` or
+    /// `$name`, see [`Regex::replace`](regex::Regex::replace).
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// use regex::Regex;
+    ///
+    /// // Strip the version segment, e.g. `/v12/assets/app.js` to `/assets/app.js`.
+    /// let service = ServeDir::new(&ASSETS_DIR)
+    ///     .rewrite_regex(Regex::new(r"^/v\d+/(.*)$").unwrap(), "/$1");
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn rewrite_regex(mut self, regex: regex::Regex, replacement: &str) -> Self {
+        self.rewrites.push(Rewrite::Regex {
+            regex,
+            replacement: replacement.to_owned(),
+        });
+        self
     }
-    dir.get_dir(path).is_some()
-}
 
-fn append_slash_on_path(uri: Uri) -> Uri {
-    let http::uri::Parts {
-        scheme,
-        authority,
-        path_and_query,
-        ..
-    } = uri.into_parts();
+    /// Customize the headers of responses serving a file.
+    ///
+    /// The callback receives the served file, the parts of the request and the response headers
+    /// right before the response is returned, so it can add or replace headers depending on
+    /// the file, e.g. `X-Robots-Tag` for some paths.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// use http::{header::HeaderName, HeaderValue};
+    ///
+    /// let service = ServeDir::new(&ASSETS_DIR).response_headers(|file, _req, headers| {
+    ///     if file.path().is_some_and(|path| path.starts_with("drafts")) {
+    ///         headers.insert(
+    ///             HeaderName::from_static("x-robots-tag"),
+    ///             HeaderValue::from_static("noindex"),
+    ///         );
+    ///     }
+    /// });
+    /// ```
+    pub fn response_headers<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ResolvedFile<'_>, &Parts, &mut HeaderMap) + Send + Sync + 'static,
+    {
+        self.response_headers = Some(ResponseHeaders::new(f));
+        self
+    }
 
-    let mut builder = Uri::builder();
-    if let Some(scheme) = scheme {
-        builder = builder.scheme(scheme);
+    /// Serve files matching the glob `pattern` as downloads, with `Content-Disposition: attachment`.
+    ///
+    /// See [`ServeDir::allow`] for the pattern syntax, use `*` to match all files. The file name
+    /// is included, encoded as described in RFC 6266 so that non-ASCII names are preserved.
+    pub fn attachment(mut self, pattern: &str) -> Self {
+        self.attachments.push(Pattern::new(pattern));
+        self
     }
-    if let Some(authority) = authority {
-        builder = builder.authority(authority);
+
+    /// Add security headers to served files, see [`SecurityHeaders`] for the presets.
+    ///
+    /// HTML documents get the document headers, such as `Content-Security-Policy`, in addition
+    /// to the headers for all files. Headers set by other means, e.g. a `_headers` file, win.
+    pub fn security_headers(mut self, headers: SecurityHeaders) -> Self {
+        self.security_headers = Some(headers);
+        self
     }
-    if let Some(path_and_query) = path_and_query {
-        if let Some(query) = path_and_query.query() {
-            builder = builder.path_and_query(format!("{}/?{}", path_and_query.path(), query));
-        } else {
-            builder = builder.path_and_query(format!("{}/", path_and_query.path()));
-        }
-    } else {
-        builder = builder.path_and_query("/");
+
+    /// Serve the headers that make documents cross-origin isolated, as required for
+    /// `SharedArrayBuffer` in WebAssembly apps.
+    ///
+    /// HTML documents and scripts get `Cross-Origin-Opener-Policy: same-origin` and
+    /// `Cross-Origin-Embedder-Policy: require-corp`. All other files, and scripts, get
+    /// `Cross-Origin-Resource-Policy: same-origin`, so that isolated documents can load them.
+    /// `.wasm` files are served as `application/wasm`, which is needed for streaming compilation.
+    /// Headers set by other means, e.g. a `_headers` file, win.
+    ///
+    /// Defaults to `false`.
+    pub fn cross_origin_isolation(mut self, isolate: bool) -> Self {
+        self.cross_origin_isolation = isolate;
+        self
     }
 
-    builder.build().unwrap()
-}
+    /// Allow cross-origin requests for files matching the glob `pattern`, see [`Cors`].
+    ///
+    /// See [`ServeDir::allow`] for the pattern syntax, use `*` to match all files. If multiple
+    /// patterns match a file, the last one wins.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::{Cors, ServeDir};
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// let service = ServeDir::new(&ASSETS_DIR)
+    ///     .cors("*.{woff,woff2}", Cors::any())
+    ///     .cors("modules/**", Cors::origins(["https://example.com"]));
+    /// ```
+    pub fn cors(mut self, pattern: &str, cors: Cors) -> Self {
+        self.cors.push((Pattern::new(pattern), cors));
+        self
+    }
 
-enum Inner {
-    File(&'static File<'static>, HeaderValue, usize),
-    Redirect(HeaderValue),
-    NotFound,
-    Invalid,
-    #[cfg(feature = "metadata")]
-    NotModified,
-}
+    /// Use digests computed at compile time by [`include_digests!`](crate::include_digests),
+    /// see [`ServeDir::integrity`].
+    ///
+    /// With overlays, add the digests of each layer. Digests of files that differ from the
+    /// embedded ones, e.g. because they were computed with other filters, are ignored.
+    pub fn with_digests(mut self, digests: &'static Digests) -> Self {
+        self.digests.push(digests);
+        self
+    }
 
-/// Response future of [`ServeDir`].
-pub struct ResponseFuture {
-    inner: Option<Inner>,
-}
+    /// Add RFC 9530 digest headers to file responses.
+    ///
+    /// Responses carry `Repr-Digest`, and `Content-Digest` if the request asks for it with
+    /// `Want-Content-Digest`. The algorithm, SHA-256 or SHA-512, is picked from the
+    /// `Want-Repr-Digest` and `Want-Content-Digest` preferences of the request. The digests
+    /// are taken from [`ServeDir::with_digests`], files without digests get no digest headers.
+    ///
+    /// The digests describe the embedded bytes, so don't enable this behind a layer that
+    /// compresses responses.
+    ///
+    /// Defaults to `false`.
+    pub fn digest_headers(mut self, enable: bool) -> Self {
+        self.digest_headers = enable;
+        self
+    }
 
-impl Future for ResponseFuture {
-    type Output = Result<Response<ResponseBody>, Infallible>;
+    /// Serve every file at a fingerprinted path as well, e.g. `app.js` at
+    /// `app.3f9a1c0b5e7d2a64.js`.
+    ///
+    /// The fingerprint is the start of the SHA-256 digest of the file as it is served, e.g. after
+    /// [`ServeDir::base_path`] rewrote it, see [`ServeDir::asset_url`] to get the fingerprinted
+    /// path for templates. Responses for fingerprinted paths are cached
+    /// forever with `Cache-Control: public, max-age=31536000, immutable`, unless another
+    /// `Cache-Control` header is set. Requests with an outdated fingerprint get a
+    /// `404 Not Found`, see [`ServeDir::redirect_stale_fingerprints`].
+    ///
+    /// Defaults to `false`.
+    pub fn fingerprint_paths(mut self, enable: bool) -> Self {
+        self.fingerprints = enable.then(Default::default);
+        self
+    }
 
-    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.inner.take().unwrap() {
-            Inner::File(file, mime, chunk_size) => {
-                let body = AsyncReadBody::with_capacity(file.contents(), chunk_size).boxed();
-                let body = ResponseBody(body);
+    /// Redirect requests with an outdated fingerprint to the current fingerprinted path,
+    /// e.g. for pages that were cached before a deployment.
+    ///
+    /// Only has an effect with [`ServeDir::fingerprint_paths`]. Defaults to `false`.
+    pub fn redirect_stale_fingerprints(mut self, redirect: bool) -> Self {
+        self.redirect_stale_fingerprints = redirect;
+        self
+    }
 
-                let mut res = Response::new(body);
-                res.headers_mut().insert(header::CONTENT_TYPE, mime);
+    /// Add `Link` headers with preload hints to HTML documents, for the scripts, stylesheets
+    /// and fonts they reference.
+    ///
+    /// Module scripts get `rel=modulepreload`, classic scripts and stylesheets
+    /// `rel=preload`, and `<link rel="preload">` elements, e.g. for fonts, are copied as is.
+    /// Only resources of the same origin are included. Each document is scanned once, the
+    /// first time it is served.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/wasm");
+    /// // `index.html` is served with `Link: <app.js>; rel=modulepreload`
+    /// let service = ServeDir::new(&ASSETS_DIR).preload_links(true);
+    /// ```
+    ///
+    /// Defaults to `false`.
+    pub fn preload_links(mut self, enable: bool) -> Self {
+        self.preload_links = enable.then(Default::default);
+        self
+    }
 
-                #[cfg(feature = "metadata")]
-                if let Some(metadata) = file.metadata() {
-                    let modified = httpdate::HttpDate::from(metadata.modified()).to_string();
-                    let value = HeaderValue::from_str(&modified).expect("SystemTime format");
-                    res.headers_mut().insert(header::LAST_MODIFIED, value);
-                }
+    /// Rewrite absolute URLs in HTML documents and stylesheets to start with `prefix`, for a
+    /// site that is built for `/` but mounted under a path, e.g. with `nest_service("/app", ..)`.
+    ///
+    /// In HTML documents this rewrites `src`, `href`, `srcset`, `action`, `poster` and
+    /// `formaction` attributes, including `<base href>`, and the `url(..)` and `@import` URLs of
+    /// `<style>` elements and `style` attributes. In stylesheets it rewrites `url(..)` and
+    /// `@import` URLs. URLs of other hosts, relative URLs and scripts are left alone. Each file
+    /// is rewritten once, the first time it is served.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// // `<script src="/app.js">` is served as `<script src="/app/app.js">`
+    /// let service = ServeDir::new(&ASSETS_DIR).base_path("/app");
+    /// let app: axum::Router = axum::Router::new().nest_service("/app", service);
+    /// ```
+    ///
+    /// Redirects to paths on this host, e.g. to add a trailing slash to a directory, are
+    /// prefixed as well.
+    ///
+    /// Rewritten files no longer match their digests, so they get no digest headers and
+    /// [`ServeDir::integrity`] returns `None` for them.
+    pub fn base_path(mut self, prefix: &str) -> Self {
+        self.base_path = BasePath::new(prefix).map(Arc::new);
+        self.reset_transformed();
+        self
+    }
 
-                Poll::Ready(Ok(res))
-            }
-            Inner::Redirect(location) => {
-                let res = Response::builder()
-                    .header(http::header::LOCATION, location)
-                    .status(StatusCode::TEMPORARY_REDIRECT)
-                    .body(empty_body())
-                    .unwrap();
+    /// Expand server-side includes, e.g. `<!--#include virtual="/partials/header.html" -->`,
+    /// in HTML documents, to share headers and footers between pages.
+    ///
+    /// `virtual` paths starting with `/` are relative to the root of the directory, other
+    /// `virtual` and `file` paths to the including document. Included files are looked up
+    /// across all layers and may include other files themselves. Hidden files can be included,
+    /// so partials can be hidden with [`ServeDir::deny`].
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/includes/site");
+    /// let service = ServeDir::new(&ASSETS_DIR).deny("partials").with_includes()?;
+    /// # Ok::<(), tower_serve_static::IncludeError>(())
+    /// ```
+    ///
+    /// The documents are expanded once, here, and again for every later [`ServeDir::overlay`].
+    /// Includes of missing files, outside of the directory or that include themselves return an
+    /// error pointing to the offending line.
+    pub fn with_includes(mut self) -> Result<Self, IncludeError> {
+        self.includes = Some(Arc::new(Includes::expand(&self.layers)?));
+        self.reset_transformed();
+        Ok(self)
+    }
 
-                Poll::Ready(Ok(res))
-            }
-            Inner::NotFound | Inner::Invalid => {
-                let res = Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(empty_body())
-                    .unwrap();
+    /// Inject runtime configuration into HTML documents, e.g. the URLs of the environment the
+    /// app is deployed to, instead of baking them into the build.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::{AppConfig, ServeDir};
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// let config = AppConfig::new().global("__APP_CONFIG__", r#"{"apiUrl":"/api"}"#);
+    /// let service = ServeDir::new(&ASSETS_DIR).app_config(config);
+    /// ```
+    ///
+    /// Each document is processed once, the first time it is served, and the same bytes are
+    /// sent to every request afterwards.
+    ///
+    /// Documents that are changed by the configuration or by [`ServeDir::base_path`] are served
+    /// with a `Content-Length` and an `ETag` of the served bytes, and `If-None-Match` requests
+    /// are answered with `304 Not Modified`. They get no `Last-Modified`, since the embedded
+    /// file didn't change when the configuration did.
+    pub fn app_config(mut self, config: AppConfig) -> Self {
+        self.app_config = Some(Arc::new(config));
+        self.reset_transformed();
+        self
+    }
 
-                Poll::Ready(Ok(res))
-            }
-            #[cfg(feature = "metadata")]
-            Inner::NotModified => {
-                let res = Response::builder()
-                    .status(StatusCode::NOT_MODIFIED)
-                    .body(empty_body())
-                    .unwrap();
+    /// Add a fresh nonce to the `<script>` and `<style>` elements of every HTML response, and
+    /// send `policy` as its `Content-Security-Policy` with each `{nonce}` replaced by it.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// // `<script src="app.js">` is served as `<script nonce="..." src="app.js">`
+    /// let service = ServeDir::new(&ASSETS_DIR).csp_nonce(
+    ///     "script-src 'nonce-{nonce}' 'strict-dynamic'; style-src 'self' 'nonce-{nonce}'; \
+    ///      object-src 'none'; base-uri 'none'",
+    /// );
+    /// ```
+    ///
+    /// Each document is scanned once, the first time it is served, so requests only splice
+    /// the nonce into the known positions. Elements that already have a `nonce` get the fresh
+    /// one instead, and other files aren't processed at all.
+    ///
+    /// The policy replaces a `Content-Security-Policy` of [`ServeDir::security_headers`] or a
+    /// `_headers` file. Since every response is different, HTML documents are served with
+    /// `Cache-Control: no-store` unless it is set otherwise, and without `ETag` or
+    /// `Last-Modified`.
+    ///
+    /// Requires the `csp-nonce` feature.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `policy` isn't a valid header value.
+    #[cfg(feature = "csp-nonce")]
+    pub fn csp_nonce(mut self, policy: &str) -> Self {
+        self.csp_nonce = Some(Arc::new(CspNonce::new(policy)));
+        self.reset_transformed();
+        self
+    }
 
-                Poll::Ready(Ok(res))
-            }
+    fn exists(&self, path: &Path) -> bool {
+        self.is_dir(path) || self.get_file(path).is_some()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        if path.as_os_str() == std::ffi::OsStr::new("") {
+            return true;
         }
+        self.index().visible_dirs.contains(path)
     }
-}
 
-fn empty_body() -> ResponseBody {
-    let body = Empty::new().map_err(|err| match err {}).boxed();
-    ResponseBody(body)
-}
+    // Forget the transformed files and the fingerprints of their contents, after a change to
+    // how files are transformed.
+    fn reset_transformed(&mut self) {
+        self.transformed = Arc::default();
+        if self.fingerprints.is_some() {
+            self.fingerprints = Some(Arc::default());
+        }
+    }
 
-opaque_body! {
-    /// Response body for [`ServeDir`].
-    pub type ResponseBody = BoxBody<Bytes, io::Error>;
-}
+    // The fingerprint of `file`, from the bytes it is served with.
+    fn fingerprint(&self, fingerprints: &Fingerprints, file: &'static File<'static>) -> String {
+        fingerprints.get(file.path(), file.contents(), || {
+            let mime = super::guess_mime(file.path(), file.contents());
+            match self.transform(file, &mime) {
+                Some(transformed) => Sha256::digest(&transformed.contents).into(),
+                None => self.digests(file).map_or_else(
+                    || Sha256::digest(file.contents()).into(),
+                    |digests| digests.sha256,
+                ),
+            }
+        })
+    }
 
-#[cfg(test)]
-mod tests {
-    #[allow(unused_imports)]
-    use super::*;
-    use http::{Request, StatusCode};
-    use http_body::Body as HttpBody;
-    use include_dir::include_dir;
-    use tower::ServiceExt;
+    fn digests(&self, file: &File<'_>) -> Option<Integrity> {
+        let path = file.path().to_str()?;
+        self.digests
+            .iter()
+            .rev()
+            .find_map(|digests| digests.get(path, file.contents().len()))
+    }
 
-    static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    // The transformed `file`, `None` if it is served as is.
+    fn transform(&self, file: &'static File<'static>, mime: &HeaderValue) -> Option<Transformed> {
+        let html = security::is_html(mime);
+        let expanded = self
+            .includes
+            .as_ref()
+            .and_then(|includes| includes.get(file.path()));
+        let app_config = self.app_config.as_ref().filter(|_| html);
+        #[cfg(feature = "csp-nonce")]
+        let nonced = html && self.csp_nonce.is_some();
+        #[cfg(not(feature = "csp-nonce"))]
+        let nonced = false;
+        if expanded.is_none() && self.base_path.is_none() && app_config.is_none() && !nonced
+            || !html && !security::is_css(mime)
+        {
+            return None;
+        }
 
-    #[tokio::test]
-    async fn basic() {
-        let svc = ServeDir::new(&ASSETS_DIR);
+        self.transformed
+            .get_or_insert_with_path(file.path(), file.contents(), || {
+                let mut text = match expanded {
+                    Some(expanded) => Cow::Owned(expanded.to_owned()),
+                    None => Cow::Borrowed(std::str::from_utf8(file.contents()).ok()?),
+                };
+                if let Some(rewritten) = self
+                    .base_path
+                    .as_ref()
+                    .and_then(|base_path| base_path.rewrite(&text, html))
+                {
+                    text = Cow::Owned(rewritten);
+                }
+                if let Some(injected) = app_config.and_then(|config| config.inject(&text)) {
+                    text = Cow::Owned(injected);
+                }
 
-        let req = Request::builder()
-            .uri("/text.txt")
-            .body(http_body_util::Empty::<Bytes>::new())
-            .unwrap();
-        let res = svc.oneshot(req).await.unwrap();
+                #[cfg(feature = "csp-nonce")]
+                let nonces = nonced.then(|| Arc::from(nonce::insertion_points(&text)));
 
-        assert_eq!(res.status(), StatusCode::OK);
-        assert_eq!(res.headers()["content-type"], "text/plain");
-        #[cfg(not(feature = "metadata"))]
-        {
-            assert!(!res.headers().contains_key("last-modified"));
-        }
-        #[cfg(feature = "metadata")]
-        {
-            assert!(res.headers().contains_key("last-modified"));
+                let contents = match text {
+                    Cow::Borrowed(_) if !nonced => return None,
+                    Cow::Borrowed(_) => Bytes::from_static(file.contents()),
+                    Cow::Owned(text) => Bytes::from(text),
+                };
+                #[cfg(feature = "csp-nonce")]
+                let content_length = match &nonces {
+                    Some(nonces) => nonce::spliced_len(contents.len(), nonces),
+                    None => contents.len(),
+                };
+                #[cfg(not(feature = "csp-nonce"))]
+                let content_length = contents.len();
+                let etag = (!nonced).then(|| {
+                    let etag = format!("\"{:016x}\"", fnv1a(&contents));
+                    HeaderValue::from_str(&etag).expect("hex digits")
+                });
+                Some(Transformed {
+                    contents,
+                    #[cfg(feature = "csp-nonce")]
+                    nonces,
+                    content_length: HeaderValue::from(content_length),
+                    etag,
+                })
+            })
+    }
+
+    fn get_file(&self, path: &Path) -> Option<&'static File<'static>> {
+        if !self.policy.is_visible(path) {
+            return None;
         }
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get_file(path))
+    }
 
-        let body = body_into_text(res.into_body()).await;
+    // A redirect to `location`, under the base path if it is a path on this host.
+    fn redirect(&self, location: &str, status: StatusCode) -> Resolution {
+        let location = match &self.base_path {
+            Some(base_path) => base_path.prefix(location),
+            None => Cow::Borrowed(location),
+        };
+        let location = HeaderValue::from_str(&location).expect("valid location");
+        Resolution::redirect(location, status)
+    }
 
-        let contents = std::fs::read_to_string("./tests/assets/text.txt").unwrap();
-        assert_eq!(body, contents);
+    fn index(&self) -> &Index {
+        self.index.get_or_init(|| {
+            let mut variants = HashMap::<PathBuf, Vec<(String, PathBuf)>>::new();
+            if self.language.is_some() {
+                for layer in &self.layers {
+                    self.collect_variants(layer, &mut variants);
+                }
+            }
+
+            Index {
+                visible_dirs: self.policy.visible_dirs(&self.layers),
+                variants,
+            }
+        })
     }
 
-    #[cfg(feature = "metadata")]
+    fn collect_variants(
+        &self,
+        dir: &Dir<'static>,
+        variants: &mut HashMap<PathBuf, Vec<(String, PathBuf)>>,
+    ) {
+        for file in dir.files() {
+            let Some((name, tag)) = file
+                .path()
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(language::variant_of)
+            else {
+                continue;
+            };
+            if !self.policy.is_visible(file.path()) {
+                continue;
+            }
+
+            let path = file.path().with_file_name(name);
+            let variants = variants.entry(path).or_default();
+            if !variants.iter().any(|(t, _)| t.eq_ignore_ascii_case(tag)) {
+                variants.push((tag.to_owned(), file.path().to_path_buf()));
+            }
+        }
+        for dir in dir.dirs() {
+            self.collect_variants(dir, variants);
+        }
+    }
+}
+
+impl ServeDir {
+    /// Resolve a request the same way the service does, without building a response.
+    ///
+    /// This runs the exact same lookup, path validation, rewrites, index handling and
+    /// negotiation as [`Service::call`], so it can be used with other frameworks, in tests or
+    /// in build scripts. Only the headers and the URI of the request are used.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::{ServeDir, ServeOutcome};
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+    /// let service = ServeDir::new(&ASSETS_DIR);
+    ///
+    /// let req = http::Request::builder().uri("/subfolder").body(()).unwrap();
+    /// let resolution = service.resolve(&req);
+    /// assert_eq!(resolution.outcome(), ServeOutcome::Redirect);
+    /// assert_eq!(resolution.headers()["location"], "/subfolder/");
+    /// ```
+    pub fn resolve<B>(&self, req: &Request<B>) -> Resolution {
+        // build and validate the path
+        let path = req.uri().path();
+        let path = path.trim_start_matches('/');
+
+        let path_decoded = if let Ok(decoded_utf8) = percent_decode(path.as_ref()).decode_utf8() {
+            decoded_utf8
+        } else {
+            return Resolution::not_found();
+        };
+
+        let request_path = rewrite::apply_all(&self.rewrites, format!("/{path_decoded}"));
+
+        let mut full_path =
+            if let Some(full_path) = build_path(request_path.trim_start_matches('/')) {
+                full_path
+            } else {
+                return Resolution::not_found();
+            };
+
+        let mut language_prefix = None;
+        if let Some((language, rest)) = self
+            .language
+            .as_ref()
+            .and_then(|negotiation| negotiation.strip_prefix(&full_path))
+        {
+            language_prefix = Some(language);
+            full_path = rest;
+        }
+
+        let mut immutable = false;
+        if let Some(fingerprints) = &self.fingerprints {
+            if let Some((original, requested)) =
+                fingerprint::strip(&full_path).filter(|_| !self.exists(&full_path))
+            {
+                if let Some(file) = self.get_file(&original) {
+                    let current = self.fingerprint(fingerprints, file);
+                    if requested != current {
+                        let path = req.uri().path();
+                        let Some(at) = path
+                            .rfind(&requested)
+                            .filter(|_| self.redirect_stale_fingerprints)
+                        else {
+                            return Resolution::not_found();
+                        };
+
+                        let mut location =
+                            format!("{}{current}{}", &path[..at], &path[at + requested.len()..]);
+                        if let Some(query) = req.uri().query() {
+                            location.push('?');
+                            location.push_str(query);
+                        }
+                        return self.redirect(&location, StatusCode::TEMPORARY_REDIRECT);
+                    }
+
+                    full_path = original;
+                    immutable = true;
+                }
+            }
+        }
+
+        let mut status = StatusCode::OK;
+        let mut headers = HeaderMap::new();
+        let mut rewritten = false;
+        if let Some(rules) = &self.netlify_rules {
+            rules.apply_headers(&request_path, &mut headers);
+
+            if let Some(redirect) = rules.redirect(&request_path) {
+                if redirect.force || !self.exists(&full_path) {
+                    if !redirect.is_rewrite() {
+                        let location = redirect_location(&redirect.to, req.uri().query());
+                        return self.redirect(&location, redirect.status);
+                    }
+
+                    let target = redirect.to.split('?').next().unwrap_or_default();
+                    full_path = if let Some(full_path) =
+                        percent_decode(target.trim_start_matches('/').as_bytes())
+                            .decode_utf8()
+                            .ok()
+                            .and_then(|target| build_path(&target))
+                    {
+                        full_path
+                    } else {
+                        return Resolution::not_found();
+                    };
+                    status = redirect.status;
+                    rewritten = true;
+                }
+            }
+        }
+
+        let mut index = false;
+        if rewritten {
+            if self.append_index_html_on_directories && self.is_dir(&full_path) {
+                full_path.push("index.html");
+                index = true;
+            }
+        } else if !request_path.ends_with('/') {
+            if self.is_dir(&full_path) {
+                let location = append_slash_on_path(req.uri().clone()).to_string();
+                return self.redirect(&location, StatusCode::TEMPORARY_REDIRECT);
+            }
+        } else if self.is_dir(&full_path) {
+            if self.append_index_html_on_directories {
+                full_path.push("index.html");
+                index = true;
+            } else {
+                return Resolution::not_found();
+            }
+        }
+
+        if let Some(negotiation) = &self.language {
+            if let Some(variants) = self.index().variants.get(&full_path) {
+                headers.append(header::VARY, negotiation.vary());
+
+                let preferences = negotiation.preferences(req.headers(), language_prefix);
+                let tags = variants
+                    .iter()
+                    .map(|(tag, _)| tag.as_str())
+                    .collect::<Vec<_>>();
+                if let Some(i) = LanguageNegotiation::select(&preferences, &tags) {
+                    let (tag, path) = &variants[i];
+                    headers.insert(
+                        header::CONTENT_LANGUAGE,
+                        HeaderValue::from_str(tag).expect("language tag"),
+                    );
+                    full_path = path.clone();
+                }
+            }
+        }
+
+        let mut file = if let Some(file) = self.get_file(&full_path) {
+            file
+        } else {
+            return Resolution::not_found();
+        };
+
+        if self.negotiate_image_formats {
+            let available = ImageFormat::ALL
+                .into_iter()
+                .filter_map(|format| {
+                    let path = format.sibling(&full_path)?;
+                    let file = self.get_file(&path)?;
+                    Some((format, path, file))
+                })
+                .collect::<Vec<_>>();
+
+            if !available.is_empty() {
+                headers.append(header::VARY, HeaderValue::from_static("Accept"));
+
+                let formats = available
+                    .iter()
+                    .map(|(format, ..)| *format)
+                    .collect::<Vec<_>>();
+                if let Some((format, path, alternative)) =
+                    ImageFormat::select(req.headers(), &formats).and_then(|selected| {
+                        available
+                            .into_iter()
+                            .find(|(format, ..)| *format == selected)
+                    })
+                {
+                    if let Some(location) = format
+                        .location(req.uri().path())
+                        .and_then(|location| HeaderValue::from_str(&location).ok())
+                    {
+                        headers.insert(header::CONTENT_LOCATION, location);
+                    }
+                    full_path = path;
+                    file = alternative;
+                }
+            }
+        }
+
+        let mime = super::guess_mime(&full_path, file.contents());
+        let transformed = self.transform(file, &mime);
+        let contents = match &transformed {
+            Some(transformed) => transformed.contents.clone(),
+            None => Bytes::from_static(file.contents()),
+        };
+        let asset = ServedAsset::file(
+            if index {
+                ServeOutcome::Index
+            } else {
+                ServeOutcome::File
+            },
+            Some(file.path()),
+            mime.clone(),
+            contents,
+        );
+
+        let cors = self
+            .cors
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.matches(file.path()))
+            .map(|(_, cors)| cors);
+        if let Some(cors) = cors.filter(|_| cors::is_preflight(req)) {
+            let mut headers = HeaderMap::new();
+            cors.preflight(req.headers(), &mut headers);
+            return Resolution {
+                status: StatusCode::NO_CONTENT,
+                headers,
+                file: None,
+                asset: asset.with_outcome(ServeOutcome::Preflight),
+            };
+        }
+
+        // transformed contents are validated by their own `ETag` instead of the embedded
+        // metadata, unless they change with every response
+        let etag = transformed
+            .as_ref()
+            .and_then(|transformed| transformed.etag.clone());
+        let unmodified = match (&etag, &transformed) {
+            (Some(etag), _) => none_match(req, etag),
+            #[cfg(feature = "metadata")]
+            (None, None) => super::unmodified_since_request_condition(file, req),
+            _ => false,
+        };
+        // a 304 gets the headers of the 200 it stands for, without those of the body
+        let not_modified = status == StatusCode::OK && unmodified;
+
+        // only responses with a body get a nonce
+        #[cfg(feature = "csp-nonce")]
+        let (asset, nonce) = match (not_modified, &transformed) {
+            (
+                false,
+                Some(Transformed {
+                    contents,
+                    nonces: Some(nonces),
+                    ..
+                }),
+            ) => {
+                let nonce = nonce::generate();
+                let contents = nonce::splice(contents, nonces, &nonce);
+                (asset.with_contents(contents), Some(nonce))
+            }
+            _ => (asset, None),
+        };
+
+        headers.insert(header::CONTENT_TYPE, mime);
+        #[cfg(feature = "csp-nonce")]
+        if let (Some(csp_nonce), Some(nonce)) = (&self.csp_nonce, &nonce) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, csp_nonce.policy(nonce));
+        }
+
+        if self
+            .attachments
+            .iter()
+            .any(|pattern| pattern.matches(file.path()))
+        {
+            let name = file.path().file_name().and_then(|name| name.to_str());
+            headers.insert(header::CONTENT_DISPOSITION, disposition::attachment(name));
+        }
+
+        #[cfg(feature = "sniff")]
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+
+        if let (Some(security_headers), Some(mime)) = (&self.security_headers, asset.mime()) {
+            security_headers.apply(mime, &mut headers);
+        }
+        if let (true, Some(mime)) = (self.cross_origin_isolation, asset.mime()) {
+            security::cross_origin_isolation(mime, &mut headers);
+        }
+        if let Some(cors) = cors {
+            cors.apply(req.headers(), &mut headers);
+        }
+        if let (true, false, false, Some(integrity)) = (
+            self.digest_headers,
+            not_modified,
+            transformed.is_some(),
+            self.digests(file),
+        ) {
+            integrity.digest_headers(req.headers(), &mut headers);
+        }
+        if immutable {
+            headers
+                .entry(header::CACHE_CONTROL)
+                .or_insert_with(fingerprint::immutable);
+        }
+        if let (Some(cache), Some(mime)) = (&self.preload_links, asset.mime()) {
+            let links = security::is_html(mime)
+                .then(|| {
+                    cache.get_or_insert_with_path(file.path(), file.contents(), || {
+                        preload::links(std::str::from_utf8(asset.contents()?).ok()?)
+                    })
+                })
+                .flatten();
+            if let Some(links) = links {
+                headers.append(header::LINK, links);
+            }
+        }
+
+        if let Some(transformed) = &transformed {
+            headers.insert(header::CONTENT_LENGTH, transformed.content_length.clone());
+        }
+        if let Some(etag) = etag {
+            headers.insert(header::ETAG, etag);
+        }
+        #[cfg(feature = "csp-nonce")]
+        if nonce.is_some() {
+            headers
+                .entry(header::CACHE_CONTROL)
+                .or_insert_with(|| HeaderValue::from_static("no-store"));
+        }
+
+        if not_modified {
+            headers.remove(header::CONTENT_TYPE);
+            headers.remove(header::CONTENT_LENGTH);
+            return Resolution {
+                status: StatusCode::NOT_MODIFIED,
+                headers,
+                file: None,
+                asset: asset.with_outcome(ServeOutcome::NotModified),
+            };
+        }
+
+        #[cfg(feature = "metadata")]
+        if let Some(metadata) = file.metadata().filter(|_| transformed.is_none()) {
+            let modified = httpdate::HttpDate::from(metadata.modified()).to_string();
+            let value = HeaderValue::from_str(&modified).expect("SystemTime format");
+            headers.insert(header::LAST_MODIFIED, value);
+        }
+
+        Resolution {
+            status,
+            headers,
+            file: Some(file),
+            asset,
+        }
+    }
+
+    /// The [`Integrity`] of the file at `path`, e.g. to emit Subresource Integrity attributes in
+    /// server-rendered HTML.
+    ///
+    /// `path` is relative to the embedded directory, a leading `/` is ignored. Returns `None`
+    /// if the file doesn't exist, is hidden, or if no digests were added with
+    /// [`ServeDir::with_digests`].
+    ///
+    /// ```
+    /// # use include_dir::Dir;
+    /// # use tower_serve_static::{include_assets, include_digests, Digests, ServeDir};
+    /// static ASSETS_DIR: Dir<'static> = include_assets!("$CARGO_MANIFEST_DIR/tests/wasm");
+    /// static DIGESTS: Digests = include_digests!("$CARGO_MANIFEST_DIR/tests/wasm");
+    ///
+    /// let service = ServeDir::new(&ASSETS_DIR).with_digests(&DIGESTS);
+    /// let integrity = service.integrity("app.js").unwrap();
+    /// let tag = format!(r#"<script src="/app.js" integrity="{}"></script>"#, integrity.sha384());
+    /// ```
+    pub fn integrity(&self, path: &str) -> Option<Integrity> {
+        let path = Path::new(path.trim_start_matches('/'));
+        let file = self.get_file(path)?;
+        if self
+            .transform(file, &super::guess_mime(path, file.contents()))
+            .is_some()
+        {
+            // the digests are of the embedded file, not of what is served
+            return None;
+        }
+        self.digests(file)
+    }
+
+    /// The fingerprinted path of the file at `path`, see [`ServeDir::fingerprint_paths`].
+    ///
+    /// A leading `/` is kept. Returns `None` if the file doesn't exist, is hidden, or if
+    /// fingerprinted paths aren't enabled.
+    ///
+    /// ```
+    /// # use include_dir::{Dir, include_dir};
+    /// # use tower_serve_static::ServeDir;
+    /// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/wasm");
+    /// let service = ServeDir::new(&ASSETS_DIR).fingerprint_paths(true);
+    ///
+    /// let url = service.asset_url("/app.js").unwrap();
+    /// let tag = format!(r#"<script type="module" src="{url}"></script>"#);
+    /// ```
+    pub fn asset_url(&self, path: &str) -> Option<String> {
+        let fingerprints = self.fingerprints.as_ref()?;
+        let relative = path.trim_start_matches('/');
+        let file = self.get_file(Path::new(relative))?;
+        let fingerprint = self.fingerprint(fingerprints, file);
+        Some(format!(
+            "{}{}",
+            &path[..path.len() - relative.len()],
+            fingerprint::insert(relative, &fingerprint)
+        ))
+    }
+
+    /// Resolve a `GET` request without headers for `path`, see [`ServeDir::resolve`].
+    ///
+    /// The path is percent-decoded like the path of a request URI. Paths that aren't valid in a
+    /// URI resolve to [`ServeOutcome::NotFound`].
+    pub fn resolve_path(&self, path: &str) -> Resolution {
+        match Request::builder().uri(path).body(()) {
+            Ok(req) => self.resolve(&req),
+            Err(_) => Resolution::not_found(),
+        }
+    }
+}
+
+impl<ReqBody> Service<Request<ReqBody>> for ServeDir {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Future = ResponseFuture;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let resolution = self.resolve(&req);
+        let response_headers = self
+            .response_headers
+            .clone()
+            .filter(|_| resolution.file.is_some())
+            .map(|response_headers| (response_headers, req.into_parts().0));
+
+        ResponseFuture {
+            inner: Some(Inner {
+                resolution,
+                chunk_size: self.buf_chunk_size,
+                response_headers,
+            }),
+        }
+    }
+}
+
+// Whether the `If-None-Match` header of a `GET` or `HEAD` request matches `etag`.
+fn none_match<B>(req: &Request<B>, etag: &HeaderValue) -> bool {
+    if !matches!(*req.method(), http::Method::GET | http::Method::HEAD) {
+        return false;
+    }
+    let etag = etag.as_bytes();
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .flat_map(|value| value.as_bytes().split(|b| *b == b','))
+        .map(|candidate| candidate.trim_ascii())
+        .any(|candidate| {
+            // weak comparison, as required for `If-None-Match`
+            candidate == b"*" || candidate.strip_prefix(b"W/").unwrap_or(candidate) == etag
+        })
+}
+
+// Build a relative path from the decoded request path, rejecting `..` and backslashes.
+fn build_path(path: &str) -> Option<PathBuf> {
+    let mut full_path = PathBuf::new();
+    for seg in path.split('/') {
+        if seg.starts_with("..") || seg.contains('\\') {
+            return None;
+        }
+        full_path.push(seg);
+    }
+    Some(full_path)
+}
+
+// Characters that have to be encoded in a `Location` header.
+const LOCATION: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
+
+// The `Location` of a redirect rule, keeping the query of the request unless the target has one.
+fn redirect_location(to: &str, query: Option<&str>) -> String {
+    let mut location = utf8_percent_encode(to, LOCATION).to_string();
+    if let Some(query) = query.filter(|_| !to.contains('?')) {
+        location.push('?');
+        location.push_str(query);
+    }
+    location
+}
+
+fn append_slash_on_path(uri: Uri) -> Uri {
+    let http::uri::Parts {
+        scheme,
+        authority,
+        path_and_query,
+        ..
+    } = uri.into_parts();
+
+    let mut builder = Uri::builder();
+    if let Some(scheme) = scheme {
+        builder = builder.scheme(scheme);
+    }
+    if let Some(authority) = authority {
+        builder = builder.authority(authority);
+    }
+    if let Some(path_and_query) = path_and_query {
+        if let Some(query) = path_and_query.query() {
+            builder = builder.path_and_query(format!("{}/?{}", path_and_query.path(), query));
+        } else {
+            builder = builder.path_and_query(format!("{}/", path_and_query.path()));
+        }
+    } else {
+        builder = builder.path_and_query("/");
+    }
+
+    builder.build().unwrap()
+}
+
+/// The result of [`ServeDir::resolve`], everything needed to build the response except the body.
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    status: StatusCode,
+    headers: HeaderMap,
+    file: Option<&'static File<'static>>,
+    asset: ServedAsset,
+}
+
+impl Resolution {
+    fn not_found() -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            headers: HeaderMap::new(),
+            file: None,
+            asset: ServedAsset::new(ServeOutcome::NotFound),
+        }
+    }
+
+    fn redirect(location: HeaderValue, status: StatusCode) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::LOCATION, location);
+        Self {
+            status,
+            headers,
+            file: None,
+            asset: ServedAsset::new(ServeOutcome::Redirect),
+        }
+    }
+
+    /// How the request is answered.
+    pub fn outcome(&self) -> ServeOutcome {
+        self.asset.outcome()
+    }
+
+    /// The status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the response, e.g. `Content-Type` or `Location`.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The embedded file the body is served from, `None` if the body is empty.
+    ///
+    /// The body may differ from the contents of the file, e.g. with
+    /// [`ServeDir::base_path`], see [`Resolution::body`].
+    pub fn file(&self) -> Option<&'static File<'static>> {
+        self.file
+    }
+
+    /// The body that is sent, `None` if the body is empty.
+    ///
+    /// The `Content-Length`, `ETag` and `Content-Security-Policy` headers describe these bytes.
+    pub fn body(&self) -> Option<&Bytes> {
+        self.file.and(self.asset.contents())
+    }
+
+    /// The resolved asset, as added to the response extensions.
+    pub fn asset(&self) -> &ServedAsset {
+        &self.asset
+    }
+}
+
+struct Inner {
+    resolution: Resolution,
+    chunk_size: usize,
+    response_headers: Option<(ResponseHeaders, Parts)>,
+}
+
+/// The path of the served file, relative to the embedded directory.
+///
+/// Added to the extensions of responses serving a file, e.g. for logging the outcome of
+/// rewrites, index files and negotiation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolvedPath(&'static Path);
+
+impl ResolvedPath {
+    /// The path of the served file.
+    pub fn as_path(&self) -> &'static Path {
+        self.0
+    }
+}
+
+/// Response future of [`ServeDir`].
+pub struct ResponseFuture {
+    inner: Option<Inner>,
+}
+
+impl Future for ResponseFuture {
+    type Output = Result<Response<ResponseBody>, Infallible>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Inner {
+            resolution,
+            chunk_size,
+            response_headers,
+        } = self.inner.take().unwrap();
+
+        let body = match resolution.body() {
+            Some(contents) => ResponseBody(
+                AsyncReadBody::with_capacity(io::Cursor::new(contents.clone()), chunk_size).boxed(),
+            ),
+            None => empty_body(),
+        };
+
+        let mut res = Response::new(body);
+        *res.status_mut() = resolution.status;
+        *res.headers_mut() = resolution.headers;
+
+        if let (Some(file), Some((response_headers, parts))) = (resolution.file, response_headers) {
+            let mime = resolution.asset.mime().expect("mime of a resolved file");
+            let resolved = ResolvedFile {
+                path: Some(file.path()),
+                mime,
+                contents: file.contents(),
+                #[cfg(feature = "metadata")]
+                metadata: file.metadata(),
+            };
+            response_headers.apply(&resolved, &parts, res.headers_mut());
+        }
+
+        if let Some(path) = resolution.asset.path() {
+            res.extensions_mut().insert(ResolvedPath(path));
+        }
+        res.extensions_mut().insert(resolution.asset);
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+fn empty_body() -> ResponseBody {
+    let body = Empty::new().map_err(|err| match err {}).boxed();
+    ResponseBody(body)
+}
+
+opaque_body! {
+    /// Response body for [`ServeDir`].
+    pub type ResponseBody = BoxBody<Bytes, io::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    use http::{Request, StatusCode};
+    use http_body::Body as HttpBody;
+    use include_dir::include_dir;
+    use tower::ServiceExt;
+
+    static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+
+    #[tokio::test]
+    async fn basic() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/text.txt")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/plain");
+        #[cfg(not(feature = "metadata"))]
+        {
+            assert!(!res.headers().contains_key("last-modified"));
+        }
+        #[cfg(feature = "metadata")]
+        {
+            assert!(res.headers().contains_key("last-modified"));
+        }
+
+        let body = body_into_text(res.into_body()).await;
+
+        let contents = std::fs::read_to_string("./tests/assets/text.txt").unwrap();
+        assert_eq!(body, contents);
+    }
+
+    #[cfg(feature = "metadata")]
     #[tokio::test]
     async fn with_if_modified_since() {
         let svc = ServeDir::new(&ASSETS_DIR);
@@ -303,132 +1523,1443 @@ mod tests {
             .into();
 
         let req = Request::builder()
-            .uri("/text.txt")
-            .header(
-                header::IF_MODIFIED_SINCE,
-                HeaderValue::from_str(&modified.to_string()).unwrap(),
-            )
+            .uri("/text.txt")
+            .header(
+                header::IF_MODIFIED_SINCE,
+                HeaderValue::from_str(&modified.to_string()).unwrap(),
+            )
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            res.extensions().get::<ServedAsset>().unwrap().outcome(),
+            ServeOutcome::NotModified
+        );
+        assert!(!res.headers().contains_key("content-type"));
+        assert!(!res.headers().contains_key("last-modified"));
+        assert!(body_into_text(res.into_body()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn with_custom_chunk_size() {
+        let svc = ServeDir::new(&ASSETS_DIR).with_buf_chunk_size(1024 * 32);
+
+        let req = Request::builder()
+            .uri("/text.txt")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/plain");
+
+        let body = body_into_text(res.into_body()).await;
+
+        let contents = std::fs::read_to_string("./tests/assets/text.txt").unwrap();
+        assert_eq!(body, contents);
+    }
+
+    #[tokio::test]
+    async fn access_to_sub_dirs() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/subfolder/data.json")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/json");
+
+        let body = body_into_text(res.into_body()).await;
+
+        let contents = std::fs::read_to_string("./tests/assets/subfolder/data.json").unwrap();
+        assert_eq!(body, contents);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/not-found")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get(header::CONTENT_TYPE).is_none());
+
+        let body = body_into_text(res.into_body()).await;
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn redirect_to_trailing_slash_on_dir() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/subfolder")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let location = &res.headers()[http::header::LOCATION];
+        assert_eq!(location, "/subfolder/");
+    }
+
+    #[tokio::test]
+    async fn empty_directory_without_index() {
+        let svc = ServeDir::new(&ASSETS_DIR).append_index_html_on_directories(false);
+
+        let req = Request::new(http_body_util::Empty::<Bytes>::new());
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get(header::CONTENT_TYPE).is_none());
+
+        let body = body_into_text(res.into_body()).await;
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn root_path_with_index() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/html");
+
+        let body = body_into_text(res.into_body()).await;
+
+        let contents = std::fs::read_to_string("./tests/assets/index.html").unwrap();
+        assert_eq!(body, contents);
+    }
+
+    async fn body_into_text<B>(body: B) -> String
+    where
+        B: HttpBody<Data = bytes::Bytes> + Unpin,
+        B::Error: std::fmt::Debug,
+    {
+        let bytes = body.collect().await.unwrap().to_bytes(); //.await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn extensionless_file() {
+        let svc = ServeDir::new(&ASSETS_DIR);
+
+        let req = Request::builder()
+            .uri("/subfolder/logo")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        #[cfg(not(feature = "sniff"))]
+        {
+            assert_eq!(res.headers()["content-type"], "application/octet-stream");
+            assert!(!res.headers().contains_key("x-content-type-options"));
+        }
+        #[cfg(feature = "sniff")]
+        {
+            assert_eq!(res.headers()["content-type"], "image/png");
+            assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+        }
+    }
+
+    #[tokio::test]
+    async fn dotfiles_are_hidden_by_default() {
+        for uri in [
+            "/.env",
+            "/.well-known/security.txt",
+            "/.well-known",
+            "/.well-known/",
+        ] {
+            let svc = ServeDir::new(&ASSETS_DIR);
+
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+
+        let svc = ServeDir::new(&ASSETS_DIR).hide_dotfiles(false);
+
+        let req = Request::builder()
+            .uri("/.env")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn allow_dotfiles() {
+        let svc = ServeDir::new(&ASSETS_DIR).allow(".well-known/**");
+
+        let req = Request::builder()
+            .uri("/.well-known/security.txt")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/plain");
+
+        let req = Request::builder()
+            .uri("/.env")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn deny_glob() {
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .deny("*.json")
+            .deny("subfolder/logo");
+
+        let req = Request::builder()
+            .uri("/subfolder/data.json")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // the directory has no visible files left
+        let req = Request::builder()
+            .uri("/subfolder")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
-        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
-        assert!(!res.headers().contains_key("content-type"));
-        assert!(!res.headers().contains_key("last-modified"));
-        assert!(body_into_text(res.into_body()).await.is_empty());
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn with_custom_chunk_size() {
-        let svc = ServeDir::new(&ASSETS_DIR).with_buf_chunk_size(1024 * 32);
+    async fn deny_index() {
+        let svc = ServeDir::new(&ASSETS_DIR).deny("index.html");
 
         let req = Request::builder()
-            .uri("/text.txt")
+            .uri("/")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
-        assert_eq!(res.status(), StatusCode::OK);
-        assert_eq!(res.headers()["content-type"], "text/plain");
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
 
-        let body = body_into_text(res.into_body()).await;
+    #[tokio::test]
+    async fn filtered_embedding() {
+        static FILTERED_DIR: Dir<'static> = crate::include_assets!(
+            "$CARGO_MANIFEST_DIR/tests/assets",
+            include = ["*.txt", "*.html", "subfolder"],
+            exclude = ["*.json"],
+            max_file_size = 1024,
+        );
+        let svc = ServeDir::new(&FILTERED_DIR).hide_dotfiles(false);
+
+        for (uri, status) in [
+            ("/text.txt", StatusCode::OK),
+            ("/", StatusCode::OK),
+            ("/.well-known/security.txt", StatusCode::OK),
+            ("/subfolder/data.json", StatusCode::NOT_FOUND),
+            ("/subfolder/logo", StatusCode::NOT_FOUND),
+            ("/subfolder", StatusCode::NOT_FOUND),
+            ("/image.png", StatusCode::NOT_FOUND),
+            ("/.env", StatusCode::NOT_FOUND),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), status, "{uri}");
+        }
 
-        let contents = std::fs::read_to_string("./tests/assets/text.txt").unwrap();
-        assert_eq!(body, contents);
+        assert!(FILTERED_DIR.get_dir("subfolder").is_none());
     }
 
     #[tokio::test]
-    async fn access_to_sub_dirs() {
-        let svc = ServeDir::new(&ASSETS_DIR);
+    async fn overlay() {
+        static OVERLAY_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/overlay");
+        let svc = ServeDir::new(&ASSETS_DIR).overlay(&OVERLAY_DIR);
+
+        for (uri, path) in [
+            ("/text.txt", "./tests/overlay/text.txt"),
+            ("/subfolder/data.json", "./tests/assets/subfolder/data.json"),
+            ("/subfolder/", "./tests/overlay/subfolder/index.html"),
+            ("/", "./tests/assets/index.html"),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+
+            let body = body_into_text(res.into_body()).await;
+            let contents = std::fs::read_to_string(path).unwrap();
+            assert_eq!(body, contents, "{uri}");
+        }
 
+        // without the overlay, the directory has no index
         let req = Request::builder()
-            .uri("/subfolder/data.json")
+            .uri("/subfolder/")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = ServeDir::new(&ASSETS_DIR).oneshot(req).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn negotiate_language() {
+        static LOCALIZED_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/localized");
+        let svc = ServeDir::new(&LOCALIZED_DIR).negotiate_language(
+            LanguageNegotiation::new("en")
+                .cookie("lang")
+                .path_prefixes(["en", "de", "fr"]),
+        );
+
+        for (uri, accept_language, cookie, language) in [
+            ("/", None, None, "en"),
+            ("/", Some("de-AT, fr;q=0.9"), None, "de"),
+            ("/index.html", Some("de;q=0.5, fr"), None, "fr"),
+            ("/", Some("it"), None, "en"),
+            ("/", Some("de"), Some("lang=fr"), "fr"),
+            ("/fr/", Some("de"), Some("lang=de"), "fr"),
+        ] {
+            let mut req = Request::builder().uri(uri);
+            if let Some(accept_language) = accept_language {
+                req = req.header(header::ACCEPT_LANGUAGE, accept_language);
+            }
+            if let Some(cookie) = cookie {
+                req = req.header(header::COOKIE, cookie);
+            }
+            let req = req.body(http_body_util::Empty::<Bytes>::new()).unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers()["content-type"], "text/html");
+            assert_eq!(res.headers()["content-language"], language);
+            assert_eq!(res.headers()["vary"], "Accept-Language, Cookie");
+
+            let body = body_into_text(res.into_body()).await;
+            let contents =
+                std::fs::read_to_string(format!("./tests/localized/index.{language}.html"))
+                    .unwrap();
+            assert_eq!(body, contents);
+        }
+
+        // a 304 describes the same variant as the 200
+        #[cfg(feature = "metadata")]
+        {
+            let req = Request::builder()
+                .uri("/")
+                .header(header::ACCEPT_LANGUAGE, "de")
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+            let modified = res.headers()["last-modified"].clone();
+
+            let req = Request::builder()
+                .uri("/")
+                .header(header::ACCEPT_LANGUAGE, "de")
+                .header(header::IF_MODIFIED_SINCE, modified)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(res.headers()["content-language"], "de");
+            assert_eq!(res.headers()["vary"], "Accept-Language, Cookie");
+            assert!(!res.headers().contains_key("content-type"));
+        }
+    }
+
+    #[tokio::test]
+    async fn negotiate_language_keeps_tag_case() {
+        static LOCALIZED_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/localized");
+        let svc = ServeDir::new(&LOCALIZED_DIR).negotiate_language(LanguageNegotiation::new("en"));
+
+        for accept_language in ["pt-br", "pt", "PT-BR"] {
+            let req = Request::builder()
+                .uri("/about.html")
+                .header(header::ACCEPT_LANGUAGE, accept_language)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers()["content-language"], "pt-BR");
+        }
+    }
+
+    #[tokio::test]
+    async fn negotiate_language_without_variants() {
+        let svc = ServeDir::new(&ASSETS_DIR).negotiate_language(LanguageNegotiation::new("en"));
+
+        let req = Request::builder()
+            .uri("/text.txt")
+            .header(header::ACCEPT_LANGUAGE, "de")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
         assert_eq!(res.status(), StatusCode::OK);
-        assert_eq!(res.headers()["content-type"], "application/json");
+        assert!(!res.headers().contains_key("content-language"));
+        assert!(!res.headers().contains_key("vary"));
+    }
 
-        let body = body_into_text(res.into_body()).await;
+    #[tokio::test]
+    async fn negotiate_image_formats() {
+        static IMAGES_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/images");
+        let svc = ServeDir::new(&IMAGES_DIR).negotiate_image_formats(true);
+
+        for (uri, accept, content_type, location) in [
+            (
+                "/hero.png",
+                "image/avif,image/webp,*/*",
+                "image/avif",
+                Some("hero.avif"),
+            ),
+            (
+                "/hero.png",
+                "image/webp,*/*",
+                "image/webp",
+                Some("hero.webp"),
+            ),
+            ("/hero.png", "image/*,*/*;q=0.8", "image/png", None),
+            (
+                "/logo.png",
+                "image/avif,image/webp,*/*",
+                "image/webp",
+                Some("logo.webp"),
+            ),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .header(header::ACCEPT, accept)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers()["content-type"], content_type);
+            assert_eq!(res.headers()["vary"], "Accept");
+            assert_eq!(
+                res.headers()
+                    .get(header::CONTENT_LOCATION)
+                    .map(|location| location.to_str().unwrap()),
+                location
+            );
+        }
 
-        let contents = std::fs::read_to_string("./tests/assets/subfolder/data.json").unwrap();
-        assert_eq!(body, contents);
+        // a 304 still varies on `Accept`
+        #[cfg(feature = "metadata")]
+        {
+            let req = Request::builder()
+                .uri("/hero.png")
+                .header(header::ACCEPT, "image/webp,*/*")
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+            let modified = res.headers()["last-modified"].clone();
+
+            let req = Request::builder()
+                .uri("/hero.png")
+                .header(header::ACCEPT, "image/webp,*/*")
+                .header(header::IF_MODIFIED_SINCE, modified)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(res.headers()["vary"], "Accept");
+            assert_eq!(res.headers()["content-location"], "hero.webp");
+            assert!(!res.headers().contains_key("content-type"));
+        }
+
+        // disabled by default
+        let req = Request::builder()
+            .uri("/hero.png")
+            .header(header::ACCEPT, "image/avif,image/webp,*/*")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = ServeDir::new(&IMAGES_DIR).oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()["content-type"], "image/png");
+        assert!(!res.headers().contains_key("vary"));
     }
 
     #[tokio::test]
-    async fn not_found() {
-        let svc = ServeDir::new(&ASSETS_DIR);
+    async fn netlify_rules() {
+        static NETLIFY_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/netlify");
+        let svc = ServeDir::new(&NETLIFY_DIR).with_netlify_rules().unwrap();
+
+        for (uri, status, location) in [
+            ("/old-page", StatusCode::MOVED_PERMANENTLY, "/text.txt"),
+            (
+                "/blog/2024/hello?page=2",
+                StatusCode::FOUND,
+                "/news/2024/hello?page=2",
+            ),
+            ("/posts/42", StatusCode::PERMANENT_REDIRECT, "/articles/42"),
+            (
+                "/posts/a%3Fb%23c",
+                StatusCode::PERMANENT_REDIRECT,
+                "/articles/a%3Fb%23c",
+            ),
+            ("/blog/50%25%20off", StatusCode::FOUND, "/news/50%25%20off"),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), status, "{uri}");
+            assert_eq!(res.headers()[header::LOCATION], location, "{uri}");
+        }
+
+        for (uri, status, path) in [
+            ("/", StatusCode::OK, "./tests/netlify/index.html"),
+            ("/text.txt", StatusCode::OK, "./tests/netlify/index.html"),
+            ("/app/", StatusCode::OK, "./tests/netlify/app/index.html"),
+            (
+                "/app/settings",
+                StatusCode::OK,
+                "./tests/netlify/app/index.html",
+            ),
+            (
+                "/missing",
+                StatusCode::NOT_FOUND,
+                "./tests/netlify/404.html",
+            ),
+            (
+                "/_redirects",
+                StatusCode::NOT_FOUND,
+                "./tests/netlify/404.html",
+            ),
+            (
+                "/_headers",
+                StatusCode::NOT_FOUND,
+                "./tests/netlify/404.html",
+            ),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), status, "{uri}");
+            assert_eq!(res.headers()["content-type"], "text/html", "{uri}");
+            assert_eq!(res.headers()["x-frame-options"], "DENY", "{uri}");
+            assert_eq!(
+                res.headers().contains_key(header::CACHE_CONTROL),
+                uri.starts_with("/app/"),
+                "{uri}"
+            );
+
+            let body = body_into_text(res.into_body()).await;
+            let contents = std::fs::read_to_string(path).unwrap();
+            assert_eq!(body, contents, "{uri}");
+        }
+
+        // only the files at the root are rules
+        for (uri, path) in [
+            ("/docs/_redirects", "./tests/netlify/docs/_redirects"),
+            ("/docs/_headers", "./tests/netlify/docs/_headers"),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            let body = body_into_text(res.into_body()).await;
+            let contents = std::fs::read_to_string(path).unwrap();
+            assert_eq!(body, contents, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rewrite() {
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .rewrite(|path| path.strip_prefix("/v2").map(str::to_owned))
+            .rewrite(|path| (path == "/escape").then(|| "/../Cargo.toml".to_owned()));
+
+        for (uri, status, resolved) in [
+            ("/v2/text.txt", StatusCode::OK, Some("text.txt")),
+            ("/v2/", StatusCode::OK, Some("index.html")),
+            ("/v2/subfolder", StatusCode::TEMPORARY_REDIRECT, None),
+            ("/text.txt", StatusCode::OK, Some("text.txt")),
+            ("/escape", StatusCode::NOT_FOUND, None),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), status, "{uri}");
+            assert_eq!(
+                res.extensions().get::<ResolvedPath>().copied(),
+                resolved.map(|path| ResolvedPath(Path::new(path))),
+                "{uri}"
+            );
+            if status == StatusCode::TEMPORARY_REDIRECT {
+                assert_eq!(res.headers()[header::LOCATION], "/v2/subfolder/");
+            }
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[tokio::test]
+    async fn rewrite_regex() {
+        let svc = ServeDir::new(&ASSETS_DIR).rewrite_regex(
+            regex::Regex::new(r"^/v\d+/assets/(?<rest>.*)$").unwrap(),
+            "/$rest",
+        );
 
         let req = Request::builder()
-            .uri("/not-found")
+            .uri("/v12/assets/subfolder/data.json")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
-        assert_eq!(res.status(), StatusCode::NOT_FOUND);
-        assert!(res.headers().get(header::CONTENT_TYPE).is_none());
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.extensions().get::<ResolvedPath>().unwrap().as_path(),
+            Path::new("subfolder/data.json")
+        );
+    }
 
-        let body = body_into_text(res.into_body()).await;
-        assert!(body.is_empty());
+    #[tokio::test]
+    async fn response_headers() {
+        let svc = ServeDir::new(&ASSETS_DIR).response_headers(|file, req, headers| {
+            if file.mime() == "application/json" {
+                headers.insert(
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_static("attachment"),
+                );
+            }
+            if let Some(origin) = req.headers.get(header::ORIGIN) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            }
+            headers.insert("x-file-size", HeaderValue::from(file.contents().len()));
+        });
+
+        let req = Request::builder()
+            .uri("/subfolder/data.json")
+            .header(header::ORIGIN, "https://example.com")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+
+        assert_eq!(res.headers()["content-disposition"], "attachment");
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "https://example.com"
+        );
+        let size = std::fs::metadata("./tests/assets/subfolder/data.json")
+            .unwrap()
+            .len();
+        assert_eq!(res.headers()["x-file-size"], size.to_string().as_str());
+
+        let req = Request::builder()
+            .uri("/text.txt")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+
+        assert!(!res.headers().contains_key("content-disposition"));
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
     }
 
     #[tokio::test]
-    async fn redirect_to_trailing_slash_on_dir() {
+    async fn served_asset() {
         let svc = ServeDir::new(&ASSETS_DIR);
 
+        for (uri, outcome, path) in [
+            ("/text.txt", ServeOutcome::File, Some("text.txt")),
+            ("/", ServeOutcome::Index, Some("index.html")),
+            ("/subfolder", ServeOutcome::Redirect, None),
+            ("/missing", ServeOutcome::NotFound, None),
+            ("/../Cargo.toml", ServeOutcome::NotFound, None),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+
+            let asset = res.extensions().get::<ServedAsset>().unwrap();
+            assert_eq!(asset.outcome(), outcome, "{uri}");
+            assert_eq!(asset.path(), path.map(Path::new), "{uri}");
+        }
+
         let req = Request::builder()
-            .uri("/subfolder")
+            .uri("/text.txt")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
-        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+        let asset = res.extensions().get::<ServedAsset>().unwrap().clone();
+        let contents = std::fs::read("./tests/assets/text.txt").unwrap();
+        assert_eq!(asset.size(), Some(contents.len()));
+        assert_eq!(asset.mime().unwrap(), "text/plain");
+        assert_eq!(
+            asset.hash(),
+            ServedAsset::file(
+                ServeOutcome::File,
+                None,
+                HeaderValue::from_static("text/plain"),
+                Bytes::from_static(ASSETS_DIR.get_file("text.txt").unwrap().contents()),
+            )
+            .hash()
+        );
+    }
 
-        let location = &res.headers()[http::header::LOCATION];
-        assert_eq!(location, "/subfolder/");
+    #[test]
+    fn resolve() {
+        let svc = ServeDir::new(&ASSETS_DIR).negotiate_image_formats(true);
+
+        let resolution = svc.resolve_path("/");
+        assert_eq!(resolution.outcome(), ServeOutcome::Index);
+        assert_eq!(resolution.status(), StatusCode::OK);
+        assert_eq!(resolution.headers()["content-type"], "text/html");
+        assert_eq!(resolution.file().unwrap().path(), Path::new("index.html"));
+
+        for (path, outcome, status) in [
+            ("/text.txt", ServeOutcome::File, StatusCode::OK),
+            (
+                "/subfolder",
+                ServeOutcome::Redirect,
+                StatusCode::TEMPORARY_REDIRECT,
+            ),
+            ("/missing", ServeOutcome::NotFound, StatusCode::NOT_FOUND),
+            (
+                "/../Cargo.toml",
+                ServeOutcome::NotFound,
+                StatusCode::NOT_FOUND,
+            ),
+            ("/%FF", ServeOutcome::NotFound, StatusCode::NOT_FOUND),
+            ("not a uri", ServeOutcome::NotFound, StatusCode::NOT_FOUND),
+        ] {
+            let resolution = svc.resolve_path(path);
+            assert_eq!(resolution.outcome(), outcome, "{path}");
+            assert_eq!(resolution.status(), status, "{path}");
+            assert_eq!(
+                resolution.file().is_some(),
+                outcome == ServeOutcome::File,
+                "{path}"
+            );
+        }
+
+        // request headers are taken into account
+        static IMAGES_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/images");
+        let svc = ServeDir::new(&IMAGES_DIR).negotiate_image_formats(true);
+        let req = Request::builder()
+            .uri("/hero.png")
+            .header(header::ACCEPT, "image/webp")
+            .body(())
+            .unwrap();
+        let resolution = svc.resolve(&req);
+        assert_eq!(resolution.headers()["content-type"], "image/webp");
+        assert_eq!(resolution.asset().path(), Some(Path::new("hero.webp")));
+    }
+
+    #[test]
+    fn attachment() {
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .attachment("*.txt")
+            .attachment("subfolder/**");
+
+        for (path, disposition) in [
+            ("/text.txt", Some("attachment; filename=\"text.txt\"")),
+            (
+                "/%E4%BD%A0%E5%A5%BD%E4%B8%96%E7%95%8C.txt",
+                Some(
+                    "attachment; filename=\"____.txt\"; \
+                     filename*=UTF-8''%E4%BD%A0%E5%A5%BD%E4%B8%96%E7%95%8C.txt",
+                ),
+            ),
+            (
+                "/subfolder/data.json",
+                Some("attachment; filename=\"data.json\""),
+            ),
+            ("/", None),
+        ] {
+            let resolution = svc.resolve_path(path);
+            assert_eq!(
+                resolution
+                    .headers()
+                    .get(header::CONTENT_DISPOSITION)
+                    .map(|value| value.to_str().unwrap()),
+                disposition,
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn security_headers() {
+        let svc = ServeDir::new(&ASSETS_DIR).security_headers(SecurityHeaders::strict());
+
+        let html = svc.resolve_path("/");
+        assert_eq!(html.headers()["x-content-type-options"], "nosniff");
+        assert_eq!(html.headers()["x-frame-options"], "DENY");
+        assert!(html.headers().contains_key("content-security-policy"));
+
+        let json = svc.resolve_path("/subfolder/data.json");
+        assert_eq!(json.headers()["x-content-type-options"], "nosniff");
+        assert!(json.headers().contains_key("strict-transport-security"));
+        assert!(!json.headers().contains_key("content-security-policy"));
+
+        // not added to redirects and missing files
+        let redirect = svc.resolve_path("/subfolder");
+        assert!(!redirect.headers().contains_key("x-content-type-options"));
+    }
+
+    #[test]
+    fn cross_origin_isolation() {
+        static WASM_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/wasm");
+        let svc = ServeDir::new(&WASM_DIR).cross_origin_isolation(true);
+
+        let index = svc.resolve_path("/");
+        assert_eq!(index.headers()["cross-origin-opener-policy"], "same-origin");
+        assert_eq!(
+            index.headers()["cross-origin-embedder-policy"],
+            "require-corp"
+        );
+
+        let script = svc.resolve_path("/app.js");
+        assert_eq!(
+            script.headers()["cross-origin-embedder-policy"],
+            "require-corp"
+        );
+        assert_eq!(
+            script.headers()["cross-origin-resource-policy"],
+            "same-origin"
+        );
+
+        let wasm = svc.resolve_path("/app_bg.wasm");
+        assert_eq!(wasm.headers()["content-type"], "application/wasm");
+        assert_eq!(
+            wasm.headers()["cross-origin-resource-policy"],
+            "same-origin"
+        );
+        assert!(!wasm.headers().contains_key("cross-origin-opener-policy"));
+
+        // disabled by default
+        let index = ServeDir::new(&WASM_DIR).resolve_path("/");
+        assert!(!index.headers().contains_key("cross-origin-opener-policy"));
     }
 
     #[tokio::test]
-    async fn empty_directory_without_index() {
-        let svc = ServeDir::new(&ASSETS_DIR).append_index_html_on_directories(false);
+    async fn cors() {
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .cors("*.txt", Cors::any())
+            .cors("subfolder/**", Cors::origins(["https://example.com"]));
+
+        let get = |uri, origin| {
+            Request::builder()
+                .uri(uri)
+                .header(header::ORIGIN, origin)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
 
-        let req = Request::new(http_body_util::Empty::<Bytes>::new());
+        let res = svc
+            .clone()
+            .oneshot(get("/text.txt", "https://other.example"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["access-control-allow-origin"], "*");
+
+        let res = svc
+            .clone()
+            .oneshot(get("/subfolder/data.json", "https://example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            res.headers()["access-control-allow-origin"],
+            "https://example.com"
+        );
+        assert_eq!(res.headers()["vary"], "Origin");
+
+        let res = svc
+            .clone()
+            .oneshot(get("/index.html", "https://example.com"))
+            .await
+            .unwrap();
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+        // preflight
+        let req = Request::builder()
+            .method(http::Method::OPTIONS)
+            .uri("/subfolder/data.json")
+            .header(header::ORIGIN, "https://example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "range")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
         let res = svc.oneshot(req).await.unwrap();
 
-        assert_eq!(res.status(), StatusCode::NOT_FOUND);
-        assert!(res.headers().get(header::CONTENT_TYPE).is_none());
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            res.extensions().get::<ServedAsset>().unwrap().outcome(),
+            ServeOutcome::Preflight
+        );
+        assert_eq!(
+            res.headers()["access-control-allow-methods"],
+            "GET, HEAD, OPTIONS"
+        );
+        assert_eq!(res.headers()["access-control-allow-headers"], "range");
+        assert!(res
+            .headers()
+            .get_all(header::VARY)
+            .iter()
+            .any(|vary| vary == "Access-Control-Request-Headers, Access-Control-Request-Method"));
+        assert!(!res.headers().contains_key("content-type"));
+        assert!(body_into_text(res.into_body()).await.is_empty());
+    }
 
-        let body = body_into_text(res.into_body()).await;
-        assert!(body.is_empty());
+    #[test]
+    fn integrity() {
+        static ASSETS: Dir<'static> =
+            crate::include_assets!("$CARGO_MANIFEST_DIR/tests/assets", exclude = ["*.json"],);
+        static DIGESTS: Digests =
+            crate::include_digests!("$CARGO_MANIFEST_DIR/tests/assets", exclude = ["*.json"],);
+        static OVERLAY: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/overlay");
+        static OTHER_DIGESTS: Digests =
+            crate::include_digests!("$CARGO_MANIFEST_DIR/tests/overlay");
+
+        let svc = ServeDir::new(&ASSETS).with_digests(&DIGESTS);
+        assert_eq!(
+            svc.integrity("/text.txt").unwrap(),
+            crate::include_file!("/tests/assets/text.txt")
+                .integrity()
+                .unwrap()
+        );
+        assert!(svc.integrity("subfolder/data.json").is_none());
+        assert!(svc.integrity("missing.txt").is_none());
+        assert!(svc.clone().deny("*.txt").integrity("text.txt").is_none());
+        assert!(ServeDir::new(&ASSETS).integrity("text.txt").is_none());
+
+        // the overlay replaces `text.txt`, so the digests of the base layer don't apply
+        let svc = svc.overlay(&OVERLAY);
+        assert_ne!(
+            svc.integrity("text.txt"),
+            crate::include_file!("/tests/assets/text.txt").integrity()
+        );
+        assert_eq!(
+            svc.with_digests(&OTHER_DIGESTS).integrity("text.txt"),
+            Some(
+                crate::include_file!("/tests/overlay/text.txt")
+                    .integrity()
+                    .unwrap()
+            )
+        );
     }
 
     #[tokio::test]
-    async fn root_path_with_index() {
-        let svc = ServeDir::new(&ASSETS_DIR);
+    async fn digest_headers() {
+        static DIGESTS: Digests = crate::include_digests!("$CARGO_MANIFEST_DIR/tests/assets");
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .with_digests(&DIGESTS)
+            .digest_headers(true);
+
+        let req = Request::builder()
+            .uri("/text.txt")
+            .header("want-repr-digest", "sha-512=3")
+            .header("want-content-digest", "sha-256=1")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+        assert!(res.headers()["repr-digest"]
+            .to_str()
+            .unwrap()
+            .starts_with("sha-512=:Is4k7ynP"));
+        assert_eq!(
+            res.headers()["content-digest"],
+            "sha-256=:khdlrqzANsDeMqKRMQxMo+CrQA3spGBB9MFeB93Y3xM=:"
+        );
 
+        // the digest of `index.html` is used for the directory
         let req = Request::builder()
             .uri("/")
             .body(http_body_util::Empty::<Bytes>::new())
             .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+        let integrity = crate::include_file!("/tests/assets/index.html")
+            .integrity()
+            .unwrap();
+        assert_eq!(
+            res.headers()["repr-digest"],
+            integrity.sha256().replacen("sha256-", "sha-256=:", 1) + ":"
+        );
+
+        let req = Request::builder()
+            .uri("/missing.txt")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
         let res = svc.oneshot(req).await.unwrap();
+        assert!(!res.headers().contains_key("repr-digest"));
+    }
+
+    #[tokio::test]
+    async fn fingerprint_paths() {
+        let svc = ServeDir::new(&ASSETS_DIR).fingerprint_paths(true);
+
+        let url = svc.asset_url("/subfolder/data.json").unwrap();
+        let contents = include_bytes!("../tests/assets/subfolder/data.json");
+        let fingerprint = &format!("{:x}", Sha256::digest(contents))[..16];
+        assert_eq!(url, format!("/subfolder/data.{fingerprint}.json"));
+        assert!(svc.asset_url("text.txt").unwrap().starts_with("text."));
+        assert!(svc.asset_url("missing.txt").is_none());
+        assert!(ServeDir::new(&ASSETS_DIR).asset_url("text.txt").is_none());
+
+        let get = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
 
+        let res = svc.clone().oneshot(get(&url)).await.unwrap();
         assert_eq!(res.status(), StatusCode::OK);
-        assert_eq!(res.headers()["content-type"], "text/html");
+        assert_eq!(
+            res.headers()["cache-control"],
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            include_str!("../tests/assets/subfolder/data.json")
+        );
+
+        // the original path is still served, without immutable caching
+        let res = svc
+            .clone()
+            .oneshot(get("/subfolder/data.json"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key("cache-control"));
+
+        let res = svc
+            .clone()
+            .oneshot(get("/subfolder/data.0000000000000000.json?v=1"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = svc
+            .redirect_stale_fingerprints(true)
+            .oneshot(get("/subfolder/data.0000000000000000.json?v=1"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(res.headers()["location"], format!("{url}?v=1"));
+    }
+
+    #[cfg(feature = "vite")]
+    #[test]
+    fn vite_manifest() {
+        static DIST: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/vite");
+        static DIGESTS: Digests = crate::include_digests!("$CARGO_MANIFEST_DIR/tests/vite");
+
+        let manifest = ServeDir::new(&DIST)
+            .with_digests(&DIGESTS)
+            .vite_manifest()
+            .unwrap();
+        let entry = manifest.entry("src/main.ts").unwrap();
+        let integrity = crate::include_file!("/tests/vite/assets/main-4GNSnfF5.js")
+            .integrity()
+            .unwrap();
+        assert_eq!(
+            entry.scripts()[0].integrity(),
+            Some(integrity.sha384().as_str())
+        );
+        assert!(entry.to_string().contains(&format!(
+            r#"<script type="module" src="/assets/main-4GNSnfF5.js" integrity="{}" crossorigin></script>"#,
+            integrity.sha384()
+        )));
+        assert!(manifest.entry("src/missing.ts").is_none());
+
+        let err = ServeDir::new(&ASSETS_DIR).vite_manifest().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid Vite manifest: no `.vite/manifest.json` found"
+        );
+    }
 
+    #[tokio::test]
+    async fn preload_links() {
+        static WASM: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/wasm");
+        let svc = ServeDir::new(&WASM).preload_links(true);
+
+        let get = |uri| {
+            Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        let res = svc.clone().oneshot(get("/")).await.unwrap();
+        assert_eq!(res.headers()["link"], "<app.js>; rel=modulepreload");
+
+        let res = svc.clone().oneshot(get("/app.js")).await.unwrap();
+        assert!(!res.headers().contains_key("link"));
+
+        let res = ServeDir::new(&WASM).oneshot(get("/")).await.unwrap();
+        assert!(!res.headers().contains_key("link"));
+    }
+
+    #[test]
+    fn base_path_redirects() {
+        let location = |svc: &ServeDir, path| {
+            let resolution = svc.resolve_path(path);
+            assert_eq!(resolution.outcome(), ServeOutcome::Redirect, "{path}");
+            resolution.headers()[header::LOCATION].clone()
+        };
+
+        let svc = ServeDir::new(&ASSETS_DIR)
+            .fingerprint_paths(true)
+            .redirect_stale_fingerprints(true)
+            .base_path("/app");
+        assert_eq!(location(&svc, "/subfolder?a=b"), "/app/subfolder/?a=b");
+        let url = svc.asset_url("/subfolder/data.json").unwrap();
+        assert_eq!(
+            location(&svc, "/subfolder/data.0000000000000000.json"),
+            format!("/app{url}")
+        );
+
+        static NETLIFY_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/netlify");
+        let svc = ServeDir::new(&NETLIFY_DIR)
+            .with_netlify_rules()
+            .unwrap()
+            .base_path("/app");
+        assert_eq!(location(&svc, "/old-page"), "/app/text.txt");
+    }
+
+    #[tokio::test]
+    async fn base_path() {
+        static SITE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/base_path");
+        static DIGESTS: Digests = crate::include_digests!("$CARGO_MANIFEST_DIR/tests/base_path");
+        let svc = ServeDir::new(&SITE)
+            .with_digests(&DIGESTS)
+            .digest_headers(true)
+            .base_path("/app/");
+
+        let get = |uri| {
+            Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        let res = svc.clone().oneshot(get("/")).await.unwrap();
+        assert!(!res.headers().contains_key("repr-digest"));
+        let asset = res.extensions().get::<ServedAsset>().unwrap().clone();
         let body = body_into_text(res.into_body()).await;
+        assert_eq!(
+            body,
+            "<!DOCTYPE html>\n\
+             <link rel=\"stylesheet\" href=\"/app/style.css\">\n\
+             <script type=\"module\" src=\"/app/app.js\"></script>\n"
+        );
+        assert_eq!(asset.size(), Some(body.len()));
+        let resolution = svc.resolve_path("/");
+        assert_eq!(resolution.body().unwrap(), body.as_bytes());
+        assert_eq!(
+            resolution.file().unwrap().contents(),
+            include_bytes!("../tests/base_path/index.html")
+        );
+        assert!(svc.integrity("index.html").is_none());
+
+        let res = svc.clone().oneshot(get("/style.css")).await.unwrap();
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            "body { background: url(\"/app/background.png\") }\n"
+        );
+
+        // scripts are served as is
+        let res = svc.clone().oneshot(get("/app.js")).await.unwrap();
+        assert!(res.headers().contains_key("repr-digest"));
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            include_str!("../tests/base_path/app.js")
+        );
+        assert!(svc.integrity("app.js").is_some());
+
+        // fingerprints are derived from the served bytes
+        let fingerprinted = ServeDir::new(&SITE).fingerprint_paths(true);
+        let before = fingerprinted.asset_url("/style.css").unwrap();
+        let svc = fingerprinted.base_path("/app/");
+        let url = svc.asset_url("/style.css").unwrap();
+        assert_ne!(url, before);
+        let digest = Sha256::digest("body { background: url(\"/app/background.png\") }\n");
+        assert_eq!(url, format!("/style.{}.css", &format!("{digest:x}")[..16]));
+        let res = svc.clone().oneshot(get(&url)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
 
-        let contents = std::fs::read_to_string("./tests/assets/index.html").unwrap();
-        assert_eq!(body, contents);
+        let digest = Sha256::digest(include_bytes!("../tests/base_path/app.js"));
+        assert_eq!(
+            svc.asset_url("/app.js").unwrap(),
+            format!("/app.{}.js", &format!("{digest:x}")[..16])
+        );
     }
 
-    async fn body_into_text<B>(body: B) -> String
-    where
-        B: HttpBody<Data = bytes::Bytes> + Unpin,
-        B::Error: std::fmt::Debug,
-    {
-        let bytes = body.collect().await.unwrap().to_bytes(); //.await.unwrap();
-        String::from_utf8(bytes.to_vec()).unwrap()
+    #[tokio::test]
+    async fn app_config() {
+        static SITE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/base_path");
+        let config = |url: &str| {
+            AppConfig::new().global("__APP_CONFIG__", format!("{{\"apiUrl\":\"{url}\"}}"))
+        };
+        let svc = ServeDir::new(&SITE).app_config(config("https://api.example.com"));
+
+        let get = |uri, if_none_match: Option<&HeaderValue>| {
+            let mut req = Request::builder().uri(uri);
+            if let Some(etag) = if_none_match {
+                req = req.header("if-none-match", etag);
+            }
+            req.body(http_body_util::Empty::<Bytes>::new()).unwrap()
+        };
+
+        let res = svc.clone().oneshot(get("/", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key("last-modified"));
+        let etag = res.headers()["etag"].clone();
+        let length = res.headers()["content-length"].clone();
+        let body = body_into_text(res.into_body()).await;
+        assert_eq!(
+            body,
+            "<!DOCTYPE html>\n\
+             <link rel=\"stylesheet\" href=\"/style.css\">\n\
+             <script>window.__APP_CONFIG__ = {\"apiUrl\":\"https://api.example.com\"};</script>\
+             <script type=\"module\" src=\"/app.js\"></script>\n"
+        );
+        assert_eq!(length, body.len().to_string().as_str());
+
+        // the same bytes and validator for every request
+        let res = svc.clone().oneshot(get("/index.html", None)).await.unwrap();
+        assert_eq!(res.headers()["etag"], etag);
+
+        let res = svc.clone().oneshot(get("/", Some(&etag))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()["etag"], etag);
+        assert!(body_into_text(res.into_body()).await.is_empty());
+
+        // a new configuration invalidates cached documents
+        let svc = svc.app_config(config("https://staging.example.com"));
+        let res = svc.clone().oneshot(get("/", Some(&etag))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_ne!(res.headers()["etag"], etag);
+
+        // other files are served as is
+        let res = svc.clone().oneshot(get("/style.css", None)).await.unwrap();
+        assert!(!res.headers().contains_key("etag"));
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            include_str!("../tests/base_path/style.css")
+        );
+    }
+
+    #[cfg(feature = "csp-nonce")]
+    #[tokio::test]
+    async fn csp_nonce() {
+        static SITE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/base_path");
+        let svc = ServeDir::new(&SITE)
+            .security_headers(SecurityHeaders::strict())
+            .csp_nonce("script-src 'nonce-{nonce}'");
+
+        let get = |uri| {
+            Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        let mut nonces = Vec::new();
+        for _ in 0..2 {
+            let res = svc.clone().oneshot(get("/")).await.unwrap();
+            assert_eq!(res.headers()["cache-control"], "no-store");
+            assert!(!res.headers().contains_key("etag"));
+            let csp = res.headers()["content-security-policy"].to_str().unwrap();
+            let nonce = csp
+                .strip_prefix("script-src 'nonce-")
+                .and_then(|csp| csp.strip_suffix('\''))
+                .unwrap()
+                .to_owned();
+            let length = res.headers()["content-length"].clone();
+
+            let body = body_into_text(res.into_body()).await;
+            assert_eq!(
+                body,
+                format!(
+                    "<!DOCTYPE html>\n\
+                     <link rel=\"stylesheet\" href=\"/style.css\">\n\
+                     <script nonce=\"{nonce}\" type=\"module\" src=\"/app.js\"></script>\n"
+                )
+            );
+            assert_eq!(length, body.len().to_string().as_str());
+            nonces.push(nonce);
+        }
+        assert_ne!(nonces[0], nonces[1]);
+
+        // nonces written into the document are replaced
+        static INLINE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/csp_nonce");
+        let res = svc
+            .clone()
+            .overlay(&INLINE)
+            .oneshot(get("/inline.html"))
+            .await
+            .unwrap();
+        let csp = res.headers()["content-security-policy"].to_str().unwrap();
+        let nonce = csp
+            .strip_prefix("script-src 'nonce-")
+            .and_then(|csp| csp.strip_suffix('\''))
+            .unwrap()
+            .to_owned();
+        let length = res.headers()["content-length"].clone();
+        let body = body_into_text(res.into_body()).await;
+        assert_eq!(
+            body,
+            format!(
+                "<!DOCTYPE html>\n\
+                 <script nonce=\"{nonce}\">console.log(\"inline\")</script>\n"
+            )
+        );
+        assert_eq!(length, body.len().to_string().as_str());
+
+        // responses without a body get no nonce
+        let preflight = Request::options("/")
+            .header("origin", "https://example.com")
+            .header("access-control-request-method", "GET")
+            .body(())
+            .unwrap();
+        let resolution = svc.clone().cors("*", Cors::any()).resolve(&preflight);
+        assert_eq!(resolution.outcome(), ServeOutcome::Preflight);
+        assert!(!resolution.headers().contains_key("content-security-policy"));
+        assert_eq!(
+            resolution.asset().size(),
+            Some(include_str!("../tests/base_path/index.html").len())
+        );
+
+        // other files keep the policy of the security headers
+        let res = svc.clone().oneshot(get("/app.js")).await.unwrap();
+        assert!(!res.headers().contains_key("content-security-policy"));
+        assert!(!res.headers().contains_key("cache-control"));
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            include_str!("../tests/base_path/app.js")
+        );
+    }
+
+    #[tokio::test]
+    async fn includes() {
+        static SITE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/includes/site");
+        let svc = ServeDir::new(&SITE)
+            .deny("partials")
+            .with_includes()
+            .unwrap();
+
+        let get = |uri| {
+            Request::builder()
+                .uri(uri)
+                .body(http_body_util::Empty::<Bytes>::new())
+                .unwrap()
+        };
+
+        let nav = include_str!("../tests/includes/site/partials/nav.html");
+        let res = svc.clone().oneshot(get("/")).await.unwrap();
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            format!(
+                "<!DOCTYPE html>\n<header>{nav}</header>\n\n<main>Home</main>\n\
+                 <footer>Footer</footer>\n"
+            )
+        );
+        let res = svc.clone().oneshot(get("/docs/guide.html")).await.unwrap();
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            format!(
+                "<!DOCTYPE html>\n<header>{nav}</header>\n\n<main>Guide</main>\n\
+                 <footer>Footer</footer>\n"
+            )
+        );
+
+        // relative includes resolve against each document, even if their contents are equal
+        let res = svc.clone().oneshot(get("/blog/")).await.unwrap();
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            format!(
+                "<!DOCTYPE html>\n<header>{nav}</header>\n\n<main>Home</main>\n\
+                 <footer>Blog</footer>\n"
+            )
+        );
+
+        // partials can be hidden
+        let res = svc
+            .clone()
+            .oneshot(get("/partials/nav.html"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // overlays added afterwards replace partials as well
+        static THEME: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/includes/theme");
+        let svc = svc.overlay(&THEME);
+        let res = svc.clone().oneshot(get("/")).await.unwrap();
+        assert_eq!(
+            body_into_text(res.into_body()).await,
+            format!(
+                "<!DOCTYPE html>\n<header>{nav}</header>\n\n<main>Home</main>\n\
+                 <footer>Theme</footer>\n"
+            )
+        );
+
+        static CYCLE: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/includes/cycle");
+        let err = ServeDir::new(&CYCLE).with_includes().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "b.html:2: include cycle: a.html -> b.html -> a.html"
+        );
+
+        static MISSING: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/includes/missing");
+        let err = ServeDir::new(&MISSING).with_includes().unwrap_err();
+        assert_eq!((err.file(), err.line()), ("index.html", 3));
+        assert_eq!(
+            err.to_string(),
+            "index.html:3: included file `/partials/missing.html` doesn't exist"
+        );
     }
 
     #[tokio::test]
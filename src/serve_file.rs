@@ -1,12 +1,20 @@
-use super::{AsyncReadBody, DEFAULT_CAPACITY};
+use super::{
+    disposition,
+    integrity::Integrity,
+    response_headers::{ResolvedFile, ResponseHeaders},
+    security::SecurityHeaders,
+    served_asset::{ServeOutcome, ServedAsset},
+    AsyncReadBody, DEFAULT_CAPACITY,
+};
 use bytes::Bytes;
-use http::{header, HeaderValue, Response};
+use http::{header, request::Parts, HeaderMap, HeaderValue, Request, Response};
 use http_body::Frame;
 use http_body_util::{combinators::BoxBody, BodyExt};
 use std::{
     convert::Infallible,
     future::Future,
     io,
+    marker::PhantomData,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -17,25 +25,57 @@ use tower_service::Service;
 pub struct File {
     bytes: &'static [u8],
     mime: HeaderValue,
+    integrity: Option<Integrity>,
 }
 
 impl File {
     /// Create a new [`File`].
     pub fn new(bytes: &'static [u8], mime: HeaderValue) -> Self {
-        File { bytes, mime }
+        File {
+            bytes,
+            mime,
+            integrity: None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn with_integrity(mut self, integrity: Option<Integrity>) -> Self {
+        self.integrity = integrity;
+        self
+    }
+
+    /// The digests of the file, computed at compile time by [`include_file!`] and
+    /// [`include_file_with_mime!`].
+    ///
+    /// `None` for files created with [`File::new`], and if the path passed to the macro isn't a
+    /// string literal, e.g. `concat!(..)`.
+    ///
+    /// ```
+    /// use tower_serve_static::include_file;
+    ///
+    /// let integrity = include_file!("/README.md").integrity().unwrap();
+    /// let tag = format!(r#"<script src="/app.js" integrity="{}"></script>"#, integrity.sha384());
+    /// ```
+    pub fn integrity(&self) -> Option<Integrity> {
+        self.integrity
     }
 }
 
 /// Create a new [`File`].
 ///
-/// The `Content-Type` will be guessed from the file extension.
+/// The `Content-Type` will be guessed from the file extension. With the `sniff` feature enabled,
+/// files with an unknown extension are identified by their leading magic bytes at compile time.
 #[macro_export]
 macro_rules! include_file {
-    ($file:expr) => {
+    ($file:expr) => {{
+        const BYTES: &[u8] =
+            ::std::include_bytes!(::std::concat!(::std::env!("CARGO_MANIFEST_DIR"), $file));
+        const SNIFFED: ::std::option::Option<&str> = $crate::private::sniff(BYTES);
         $crate::File::new(
-            ::std::include_bytes!(::std::concat!(::std::env!("CARGO_MANIFEST_DIR"), $file)),
+            BYTES,
             $crate::private::mime_guess::from_path(&$file)
                 .first_raw()
+                .or(SNIFFED)
                 .map(|mime| $crate::private::http::HeaderValue::from_static(mime))
                 .unwrap_or_else(|| {
                     $crate::private::http::HeaderValue::from_str(
@@ -44,7 +84,8 @@ macro_rules! include_file {
                     .unwrap()
                 }),
         )
-    };
+        .with_integrity($crate::private::file_integrity!($crate, $file))
+    }};
 }
 
 /// Create a new [`File`] with a specific mime type.
@@ -57,30 +98,55 @@ macro_rules! include_file {
 #[macro_export]
 macro_rules! include_file_with_mime {
     ($file:expr, $mime:expr) => {
-        $crate::File {
-            bytes: ::std::include_bytes!(::std::concat!(::std::env!("CARGO_MANIFEST_DIR"), $file)),
-            mime: $crate::private::http::HeaderValue::from_str($mime.as_ref())
+        $crate::File::new(
+            ::std::include_bytes!(::std::concat!(::std::env!("CARGO_MANIFEST_DIR"), $file)),
+            $crate::private::http::HeaderValue::from_str($mime.as_ref())
                 .expect("mime isn't a valid header value"),
-        }
+        )
+        .with_integrity($crate::private::file_integrity!($crate, $file))
     };
 }
 
 /// Service that serves a file.
+///
+/// Accepts any request type. Reading the request with [`ServeFile::response_headers`] or
+/// [`ServeFile::digest_headers`] turns it into a `ServeFile<HttpRequest>`, which only accepts
+/// [`http::Request`]s.
 #[derive(Clone, Debug)]
-pub struct ServeFile {
+pub struct ServeFile<M = AnyRequest> {
     file: File,
     buf_chunk_size: usize,
+    response_headers: Option<ResponseHeaders>,
+    digest_headers: bool,
+    // Additional response headers.
+    headers: HeaderMap,
+    mode: PhantomData<M>,
 }
 
+/// Marker for a [`ServeFile`] that ignores the request, so that it accepts any request type.
+#[derive(Clone, Copy, Debug)]
+pub struct AnyRequest;
+
+/// Marker for a [`ServeFile`] that reads the request, so that it only accepts
+/// [`http::Request`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct HttpRequest;
+
 impl ServeFile {
     /// Create a new [`ServeFile`].
     pub fn new(file: File) -> Self {
         Self {
             file,
             buf_chunk_size: DEFAULT_CAPACITY,
+            response_headers: None,
+            digest_headers: false,
+            headers: HeaderMap::new(),
+            mode: PhantomData,
         }
     }
+}
 
+impl<M> ServeFile<M> {
     /// Set a specific read buffer chunk size.
     ///
     /// The default capacity is 64kb.
@@ -88,9 +154,84 @@ impl ServeFile {
         self.buf_chunk_size = chunk_size;
         self
     }
+
+    /// Serve the file as a download, with `Content-Disposition: attachment`.
+    ///
+    /// The `filename` is suggested to the client when saving the file. It is encoded as described
+    /// in RFC 6266, so non-ASCII names such as `你好世界.txt` are preserved.
+    ///
+    /// ```
+    /// use tower_serve_static::{ServeFile, include_file};
+    ///
+    /// let service = ServeFile::new(include_file!("/README.md")).as_attachment(Some("readme.md"));
+    /// ```
+    pub fn as_attachment(mut self, filename: Option<&str>) -> Self {
+        self.headers.insert(
+            header::CONTENT_DISPOSITION,
+            disposition::attachment(filename),
+        );
+        self
+    }
+
+    /// Add security headers to the response, see [`SecurityHeaders`] for the presets.
+    ///
+    /// HTML documents get the document headers, such as `Content-Security-Policy`, in addition
+    /// to the headers for all files.
+    pub fn security_headers(mut self, headers: SecurityHeaders) -> Self {
+        headers.apply(&self.file.mime, &mut self.headers);
+        self
+    }
+
+    /// Add RFC 9530 digest headers to the response, see
+    /// [`ServeDir::digest_headers`](crate::ServeDir::digest_headers).
+    ///
+    /// Uses the digests computed by [`include_file!`], files created with [`File::new`] get no
+    /// digest headers. The `Want-Repr-Digest` and `Want-Content-Digest` headers are read from the
+    /// request, so the service only accepts [`http::Request`]s.
+    ///
+    /// Defaults to `false`.
+    pub fn digest_headers(self, enable: bool) -> ServeFile<HttpRequest> {
+        let mut this = self.read_request();
+        this.digest_headers = enable;
+        this
+    }
+
+    /// Customize the headers of the response.
+    ///
+    /// The callback receives the file, the parts of the request and the response headers right
+    /// before the response is returned, so the service only accepts [`http::Request`]s. See
+    /// [`ServeDir::response_headers`](crate::ServeDir::response_headers).
+    pub fn response_headers<F>(self, f: F) -> ServeFile<HttpRequest>
+    where
+        F: Fn(&ResolvedFile<'_>, &Parts, &mut HeaderMap) + Send + Sync + 'static,
+    {
+        let mut this = self.read_request();
+        this.response_headers = Some(ResponseHeaders::new(f));
+        this
+    }
+
+    fn read_request(self) -> ServeFile<HttpRequest> {
+        ServeFile {
+            file: self.file,
+            buf_chunk_size: self.buf_chunk_size,
+            response_headers: self.response_headers,
+            digest_headers: self.digest_headers,
+            headers: self.headers,
+            mode: PhantomData,
+        }
+    }
+
+    fn future(&self, headers: HeaderMap, parts: Option<Parts>) -> ResponseFuture {
+        ResponseFuture {
+            file: Some(self.file.clone()),
+            buf_chunk_size: self.buf_chunk_size,
+            response_headers: self.response_headers.clone().zip(parts),
+            headers,
+        }
+    }
 }
 
-impl<R> Service<R> for ServeFile {
+impl<R> Service<R> for ServeFile<AnyRequest> {
     type Response = Response<ResponseBody>;
     type Error = Infallible;
     type Future = ResponseFuture;
@@ -101,10 +242,27 @@ impl<R> Service<R> for ServeFile {
     }
 
     fn call(&mut self, _req: R) -> Self::Future {
-        ResponseFuture {
-            file: Some(self.file.clone()),
-            buf_chunk_size: self.buf_chunk_size,
+        self.future(self.headers.clone(), None)
+    }
+}
+
+impl<ReqBody> Service<Request<ReqBody>> for ServeFile<HttpRequest> {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Future = ResponseFuture;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let mut headers = self.headers.clone();
+        if let (true, Some(integrity)) = (self.digest_headers, self.file.integrity) {
+            integrity.digest_headers(req.headers(), &mut headers);
         }
+
+        self.future(headers, Some(req.into_parts().0))
     }
 }
 
@@ -112,6 +270,8 @@ impl<R> Service<R> for ServeFile {
 pub struct ResponseFuture {
     file: Option<File>,
     buf_chunk_size: usize,
+    response_headers: Option<(ResponseHeaders, Parts)>,
+    headers: HeaderMap,
 }
 
 impl Future for ResponseFuture {
@@ -125,7 +285,34 @@ impl Future for ResponseFuture {
         let body = ResponseBody(body);
 
         let mut res = Response::new(body);
-        res.headers_mut().insert(header::CONTENT_TYPE, file.mime);
+        res.headers_mut()
+            .insert(header::CONTENT_TYPE, file.mime.clone());
+        res.extensions_mut().insert(ServedAsset::file(
+            ServeOutcome::File,
+            None,
+            file.mime.clone(),
+            Bytes::from_static(file.bytes),
+        ));
+
+        let headers = std::mem::take(&mut self.headers);
+        res.headers_mut().extend(headers);
+
+        #[cfg(feature = "sniff")]
+        res.headers_mut().insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+
+        if let Some((response_headers, parts)) = self.response_headers.take() {
+            let resolved = ResolvedFile {
+                path: None,
+                mime: &file.mime,
+                contents: file.bytes,
+                #[cfg(feature = "metadata")]
+                metadata: None,
+            };
+            response_headers.apply(&resolved, &parts, res.headers_mut());
+        }
 
         Poll::Ready(Ok(res))
     }
@@ -154,12 +341,28 @@ mod tests {
 
         assert_eq!(res.headers()["content-type"], "text/markdown");
 
+        let asset = res.extensions().get::<ServedAsset>().unwrap();
+        assert_eq!(asset.outcome(), ServeOutcome::File);
+        assert_eq!(asset.path(), None);
+        assert_eq!(
+            asset.size(),
+            Some(std::fs::metadata("README.md").unwrap().len() as usize)
+        );
+
         let body = res.into_body().collect().await.unwrap();
         let body = String::from_utf8(body.to_bytes().to_vec()).unwrap();
 
         assert!(body.starts_with("# Tower Serve Static"));
     }
 
+    #[tokio::test]
+    async fn any_request() {
+        let svc = ServeFile::new(include_file!("/README.md"));
+
+        let res = svc.oneshot(()).await.unwrap();
+        assert_eq!(res.headers()["content-type"], "text/markdown");
+    }
+
     #[tokio::test]
     async fn with_custom_chunk_size() {
         let svc = ServeFile::new(include_file!("/README.md")).with_buf_chunk_size(1024 * 32);
@@ -197,6 +400,141 @@ mod tests {
         assert!(body.starts_with("# Tower Serve Static"));
     }
 
+    #[tokio::test]
+    async fn extensionless_file() {
+        let svc = ServeFile::new(include_file!("/tests/assets/subfolder/logo"));
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+
+        #[cfg(not(feature = "sniff"))]
+        assert_eq!(res.headers()["content-type"], "application/octet-stream");
+        #[cfg(feature = "sniff")]
+        {
+            assert_eq!(res.headers()["content-type"], "image/png");
+            assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+        }
+    }
+
+    #[tokio::test]
+    async fn response_headers() {
+        let svc =
+            ServeFile::new(include_file!("/README.md")).response_headers(|file, req, headers| {
+                assert_eq!(file.path(), None);
+                assert_eq!(file.mime(), "text/markdown");
+                if req.uri.query() == Some("download") {
+                    headers.insert(
+                        header::CONTENT_DISPOSITION,
+                        HeaderValue::from_static("attachment"),
+                    );
+                }
+            });
+
+        let req = Request::builder()
+            .uri("/?download")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.headers()["content-disposition"], "attachment");
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+        assert!(!res.headers().contains_key("content-disposition"));
+    }
+
+    #[tokio::test]
+    async fn as_attachment() {
+        let svc = ServeFile::new(include_file!("/README.md")).as_attachment(Some("你好世界.md"));
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.headers()["content-disposition"],
+            "attachment; filename=\"____.md\"; filename*=UTF-8''%E4%BD%A0%E5%A5%BD%E4%B8%96%E7%95%8C.md"
+        );
+
+        let svc = ServeFile::new(include_file!("/README.md")).as_attachment(None);
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.headers()["content-disposition"], "attachment");
+    }
+
+    #[tokio::test]
+    async fn security_headers() {
+        let svc = ServeFile::new(include_file!("/tests/assets/index.html"))
+            .security_headers(SecurityHeaders::relaxed());
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+        assert_eq!(res.headers()["x-frame-options"], "SAMEORIGIN");
+        assert!(res.headers().contains_key("content-security-policy"));
+
+        let svc = ServeFile::new(include_file!("/README.md"))
+            .security_headers(SecurityHeaders::relaxed());
+
+        let res = svc
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.headers()["x-content-type-options"], "nosniff");
+        assert!(!res.headers().contains_key("content-security-policy"));
+    }
+
+    #[tokio::test]
+    async fn digest_headers() {
+        let svc = ServeFile::new(include_file!("/tests/assets/text.txt")).digest_headers(true);
+
+        let res = svc
+            .clone()
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+        assert_eq!(
+            res.headers()["repr-digest"],
+            "sha-256=:khdlrqzANsDeMqKRMQxMo+CrQA3spGBB9MFeB93Y3xM=:"
+        );
+        assert!(!res.headers().contains_key("content-digest"));
+
+        let req = Request::builder()
+            .header("want-repr-digest", "sha-256=1, sha-512=5")
+            .header("want-content-digest", "sha-256")
+            .body(http_body_util::Empty::<Bytes>::new())
+            .unwrap();
+        let res = svc.oneshot(req).await.unwrap();
+        assert!(res.headers()["repr-digest"]
+            .to_str()
+            .unwrap()
+            .starts_with("sha-512=:Is4k7ynP"));
+        assert_eq!(
+            res.headers()["content-digest"],
+            "sha-256=:khdlrqzANsDeMqKRMQxMo+CrQA3spGBB9MFeB93Y3xM=:"
+        );
+
+        let file = File::new(b"", HeaderValue::from_static("text/plain"));
+        let res = ServeFile::new(file)
+            .digest_headers(true)
+            .oneshot(Request::new(http_body_util::Empty::<Bytes>::new()))
+            .await
+            .unwrap();
+        assert!(!res.headers().contains_key("repr-digest"));
+    }
+
     // 404 is not possible with include_file!
     //
     // #[tokio::test]
@@ -0,0 +1,157 @@
+use bytes::Bytes;
+use http::HeaderValue;
+use std::{path::Path, sync::OnceLock};
+
+/// How a request was answered, see [`ServedAsset::outcome`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServeOutcome {
+    /// A file was served.
+    File,
+    /// The request was for a directory and its `index.html` was served.
+    Index,
+    /// The client was redirected, e.g. to add a trailing slash to a directory.
+    Redirect,
+    /// No file was found, or the path was invalid.
+    NotFound,
+    /// The file wasn't modified since the client cached it.
+    NotModified,
+    /// A CORS preflight request was answered.
+    Preflight,
+}
+
+/// Information about the embedded file a response was sent for.
+///
+/// Added to the extensions of every response of [`ServeDir`](crate::ServeDir) and
+/// [`ServeFile`](crate::ServeFile), so that layers such as logging, metrics or compression
+/// can tell what was served.
+///
+/// ```
+/// # use include_dir::{Dir, include_dir};
+/// # use tower_serve_static::{ServeDir, ServedAsset, ServeOutcome};
+/// # use tower::ServiceExt;
+/// # static ASSETS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/tests/assets");
+/// # async {
+/// let req = http::Request::builder().uri("/").body(())?;
+/// let res = ServeDir::new(&ASSETS_DIR).oneshot(req).await?;
+///
+/// let asset = res.extensions().get::<ServedAsset>().unwrap();
+/// assert_eq!(asset.outcome(), ServeOutcome::Index);
+/// assert_eq!(asset.path().unwrap(), std::path::Path::new("index.html"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// # };
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServedAsset {
+    outcome: ServeOutcome,
+    path: Option<&'static Path>,
+    mime: Option<HeaderValue>,
+    contents: Option<Bytes>,
+    // Computed on first use, most responses never need it.
+    hash: OnceLock<u64>,
+}
+
+impl ServedAsset {
+    pub(crate) fn new(outcome: ServeOutcome) -> Self {
+        Self {
+            outcome,
+            path: None,
+            mime: None,
+            contents: None,
+            hash: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn file(
+        outcome: ServeOutcome,
+        path: Option<&'static Path>,
+        mime: HeaderValue,
+        contents: Bytes,
+    ) -> Self {
+        Self {
+            path,
+            mime: Some(mime),
+            contents: Some(contents),
+            ..Self::new(outcome)
+        }
+    }
+
+    pub(crate) fn with_outcome(mut self, outcome: ServeOutcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    #[cfg(feature = "csp-nonce")]
+    pub(crate) fn with_contents(mut self, contents: Bytes) -> Self {
+        self.contents = Some(contents);
+        self.hash = OnceLock::new();
+        self
+    }
+
+    /// How the request was answered.
+    pub fn outcome(&self) -> ServeOutcome {
+        self.outcome
+    }
+
+    /// The path of the file relative to the embedded directory.
+    ///
+    /// `None` if no file was resolved, or for [`ServeFile`](crate::ServeFile).
+    pub fn path(&self) -> Option<&'static Path> {
+        self.path
+    }
+
+    /// The contents of the response body.
+    pub(crate) fn contents(&self) -> Option<&Bytes> {
+        self.contents.as_ref()
+    }
+
+    /// The size of the response body in bytes.
+    ///
+    /// This is the size of the embedded file, unless it was rewritten, e.g. by
+    /// [`ServeDir::base_path`](crate::ServeDir::base_path).
+    pub fn size(&self) -> Option<usize> {
+        self.contents.as_ref().map(Bytes::len)
+    }
+
+    /// The `Content-Type` of the file.
+    pub fn mime(&self) -> Option<&HeaderValue> {
+        self.mime.as_ref()
+    }
+
+    /// A 64 bit FNV-1a hash of the response body.
+    ///
+    /// The hash is stable across builds and platforms, so it can be used for cache keys.
+    pub fn hash(&self) -> Option<u64> {
+        let contents = self.contents.as_ref()?;
+        Some(*self.hash.get_or_init(|| fnv1a(contents)))
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash() {
+        // reference values of the FNV-1a specification
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+
+        let asset = ServedAsset::file(
+            ServeOutcome::File,
+            None,
+            HeaderValue::from_static("text/plain"),
+            Bytes::from_static(b"foobar"),
+        );
+        assert_eq!(asset.size(), Some(6));
+        assert_eq!(asset.hash(), Some(0x85944171f73967e8));
+        assert_eq!(ServedAsset::new(ServeOutcome::NotFound).hash(), None);
+    }
+}
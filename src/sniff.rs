@@ -0,0 +1,349 @@
+//! Magic-byte based MIME sniffing.
+//!
+//! Used as a fallback when the file extension doesn't tell us the `Content-Type`. Everything
+//! in here is a `const fn` so that [`include_file!`](crate::include_file) can sniff at compile time.
+
+/// Sniff the MIME type of `bytes` by looking at its leading magic bytes.
+///
+/// Returns `None` if the content isn't recognized.
+pub(crate) const fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    // Binary formats with a fixed signature.
+    if starts_with(bytes, 0, b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if starts_with(bytes, 0, b"\xff\xd8\xff") {
+        return Some("image/jpeg");
+    }
+    if starts_with(bytes, 0, b"GIF87a") || starts_with(bytes, 0, b"GIF89a") {
+        return Some("image/gif");
+    }
+    if starts_with(bytes, 0, b"RIFF") && starts_with(bytes, 8, b"WEBP") {
+        return Some("image/webp");
+    }
+    if starts_with(bytes, 4, b"ftypavif") || starts_with(bytes, 4, b"ftypavis") {
+        return Some("image/avif");
+    }
+    if starts_with(bytes, 0, b"%PDF-") {
+        return Some("application/pdf");
+    }
+    if starts_with(bytes, 0, b"\0asm") {
+        return Some("application/wasm");
+    }
+    if starts_with(bytes, 0, b"\x1f\x8b\x08") {
+        return Some("application/gzip");
+    }
+
+    // Text formats, ignoring a leading BOM and whitespace.
+    let start = skip_whitespace(bytes, skip_bom(bytes));
+
+    if starts_with_ignore_case(bytes, start, b"<svg") {
+        return Some("image/svg+xml");
+    }
+    if starts_with_ignore_case(bytes, start, b"<?xml")
+        && contains_ignore_case(bytes, start, b"<svg")
+    {
+        return Some("image/svg+xml");
+    }
+
+    let mut i = 0;
+    while i < HTML_PATTERNS.len() {
+        let pattern = HTML_PATTERNS[i];
+        if starts_with_ignore_case(bytes, start, pattern)
+            && is_tag_terminator(bytes, start + pattern.len())
+        {
+            return Some("text/html");
+        }
+        i += 1;
+    }
+
+    if is_json(bytes, start) {
+        return Some("application/json");
+    }
+
+    None
+}
+
+/// Tags that identify an HTML document, as in the WHATWG MIME sniffing standard.
+const HTML_PATTERNS: &[&[u8]] = &[
+    b"<!doctype html",
+    b"<html",
+    b"<head",
+    b"<script",
+    b"<iframe",
+    b"<h1",
+    b"<div",
+    b"<font",
+    b"<table",
+    b"<a",
+    b"<style",
+    b"<title",
+    b"<b",
+    b"<body",
+    b"<br",
+    b"<p",
+    b"<!--",
+];
+
+// The number of bytes to scan for an `<svg` tag after an XML declaration.
+const SVG_SCAN_LIMIT: usize = 1024;
+
+// The number of bytes to validate as JSON, a document that is valid up to here counts as JSON.
+const JSON_SCAN_LIMIT: usize = 4096;
+
+// The deepest nesting of JSON objects and arrays that is validated.
+const JSON_MAX_DEPTH: usize = 64;
+
+/// Check whether `bytes` is a JSON object or array starting at `start`, followed by nothing but
+/// whitespace. Text that merely starts with `{` or `[` isn't JSON.
+const fn is_json(bytes: &[u8], start: usize) -> bool {
+    if !(start < bytes.len() && (bytes[start] == b'{' || bytes[start] == b'[')) {
+        return false;
+    }
+
+    // Whether each open container is an object, as opposed to an array.
+    let mut objects = [false; JSON_MAX_DEPTH];
+    let mut depth = 0;
+    let mut i = start;
+    loop {
+        // A value.
+        i = skip_whitespace(bytes, i);
+        if i >= JSON_SCAN_LIMIT {
+            return true;
+        }
+        if i >= bytes.len() {
+            return false;
+        }
+        let end = match bytes[i] {
+            b'{' | b'[' => {
+                let object = bytes[i] == b'{';
+                let close = if object { b'}' } else { b']' };
+                let next = skip_whitespace(bytes, i + 1);
+                if next < bytes.len() && bytes[next] == close {
+                    Some(next + 1)
+                } else if depth == JSON_MAX_DEPTH {
+                    return false;
+                } else {
+                    objects[depth] = object;
+                    depth += 1;
+                    i = if object { json_key(bytes, next) } else { next };
+                    if i == usize::MAX {
+                        return false;
+                    }
+                    continue;
+                }
+            }
+            b'"' => json_string(bytes, i),
+            b't' if starts_with(bytes, i, b"true") => Some(i + 4),
+            b'f' if starts_with(bytes, i, b"false") => Some(i + 5),
+            b'n' if starts_with(bytes, i, b"null") => Some(i + 4),
+            b'-' | b'0'..=b'9' => json_number(bytes, i),
+            _ => None,
+        };
+        i = match end {
+            Some(end) => end,
+            None => return i >= JSON_SCAN_LIMIT,
+        };
+
+        // A separator or the end of containers.
+        loop {
+            i = skip_whitespace(bytes, i);
+            if depth == 0 {
+                return i == bytes.len();
+            }
+            if i >= JSON_SCAN_LIMIT {
+                return true;
+            }
+            if i >= bytes.len() {
+                return false;
+            }
+            let object = objects[depth - 1];
+            match bytes[i] {
+                b',' => {
+                    i = if object {
+                        json_key(bytes, i + 1)
+                    } else {
+                        i + 1
+                    };
+                    if i == usize::MAX {
+                        return false;
+                    }
+                    break;
+                }
+                b'}' if object => {
+                    depth -= 1;
+                    i += 1;
+                }
+                b']' if !object => {
+                    depth -= 1;
+                    i += 1;
+                }
+                _ => return false,
+            }
+        }
+    }
+}
+
+// The offset after an object key and its `:`, or `usize::MAX` if there is none.
+const fn json_key(bytes: &[u8], i: usize) -> usize {
+    let i = skip_whitespace(bytes, i);
+    let Some(i) = json_string(bytes, i) else {
+        return usize::MAX;
+    };
+    let i = skip_whitespace(bytes, i);
+    if i < bytes.len() && bytes[i] == b':' {
+        i + 1
+    } else {
+        usize::MAX
+    }
+}
+
+// The offset after the string starting at `i`.
+const fn json_string(bytes: &[u8], mut i: usize) -> Option<usize> {
+    if !(i < bytes.len() && bytes[i] == b'"') {
+        return None;
+    }
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => return Some(i + 1),
+            b'\\' => i += 2,
+            0..=0x1f => return None,
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// The offset after the number starting at `i`.
+const fn json_number(bytes: &[u8], mut i: usize) -> Option<usize> {
+    if bytes[i] == b'-' {
+        i += 1;
+    }
+    let digits = i;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i == digits {
+        return None;
+    }
+    while i < bytes.len() && matches!(bytes[i], b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') {
+        i += 1;
+    }
+    Some(i)
+}
+
+const fn skip_bom(bytes: &[u8]) -> usize {
+    if starts_with(bytes, 0, b"\xef\xbb\xbf") {
+        3
+    } else {
+        0
+    }
+}
+
+const fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+const fn is_tag_terminator(bytes: &[u8], i: usize) -> bool {
+    // `<!--` is complete on its own, everything else has to end the tag name.
+    i == bytes.len() || bytes[i] == b' ' || bytes[i] == b'>' || bytes[i - 1] == b'-'
+}
+
+const fn starts_with(bytes: &[u8], offset: usize, pattern: &[u8]) -> bool {
+    if bytes.len() < offset + pattern.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < pattern.len() {
+        if bytes[offset + i] != pattern[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn starts_with_ignore_case(bytes: &[u8], offset: usize, pattern: &[u8]) -> bool {
+    if bytes.len() < offset + pattern.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < pattern.len() {
+        if bytes[offset + i].to_ascii_lowercase() != pattern[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn contains_ignore_case(bytes: &[u8], offset: usize, pattern: &[u8]) -> bool {
+    let mut i = offset;
+    while i < bytes.len() && i < offset + SVG_SCAN_LIMIT {
+        if starts_with_ignore_case(bytes, i, pattern) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_signatures() {
+        let png = include_bytes!("../tests/assets/image.png");
+        assert_eq!(sniff(png), Some("image/png"));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(sniff(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"\0\0\0\x1cftypavif\0\0\0\0"), Some("image/avif"));
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff(b"\0asm\x01\0\0\0"), Some("application/wasm"));
+        assert_eq!(sniff(b"\x1f\x8b\x08\0"), Some("application/gzip"));
+    }
+
+    #[test]
+    fn text_formats() {
+        assert_eq!(sniff(b"\xef\xbb\xbf  <!DOCTYPE html>"), Some("text/html"));
+        assert_eq!(sniff(b"<HTML lang=\"en\">"), Some("text/html"));
+        assert_eq!(sniff(b"<!-- comment -->"), Some("text/html"));
+        assert_eq!(
+            sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\"?>\n<svg></svg>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff(b"\n  {\"msg\": \"Hello\"}"), Some("application/json"));
+        assert_eq!(sniff(b"[1, 2, 3]"), Some("application/json"));
+        assert_eq!(
+            sniff(b"{\"a\": [true, false, null, -1.5e3, {}, []], \"b\": \"\\\"}\"}\n"),
+            Some("application/json")
+        );
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"Hello, world!"), None);
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><feed/>"), None);
+        assert_eq!(sniff(b"<article>"), None);
+        assert_eq!(sniff(b"{{ template }}"), None);
+        assert_eq!(sniff(b"[INFO] server started"), None);
+        assert_eq!(sniff(b"{\"unterminated\": [1, 2}"), None);
+        assert_eq!(sniff(b"[1, 2] and more"), None);
+    }
+
+    #[test]
+    fn usable_in_const_context() {
+        const MIME: Option<&str> = sniff(b"%PDF-1.4");
+        assert_eq!(MIME, Some("application/pdf"));
+    }
+}
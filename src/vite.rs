@@ -0,0 +1,321 @@
+use serde_json::Value;
+use std::{collections::HashMap, error::Error, fmt};
+
+/// An error reading the Vite manifest.
+///
+/// Returned by [`ServeDir::vite_manifest`](crate::ServeDir::vite_manifest).
+#[derive(Debug)]
+pub struct ViteManifestError {
+    message: String,
+}
+
+impl ViteManifestError {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ViteManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid Vite manifest: {}", self.message)
+    }
+}
+
+impl Error for ViteManifestError {}
+
+/// The manifest of a Vite build, mapping entry points to the hashed files Vite emitted.
+///
+/// Created with [`ServeDir::vite_manifest`](crate::ServeDir::vite_manifest).
+#[derive(Clone, Debug)]
+pub struct ViteManifest {
+    chunks: HashMap<String, Chunk>,
+    // Subresource Integrity of the emitted files, by file.
+    integrity: HashMap<String, String>,
+    base: String,
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    file: String,
+    imports: Vec<String>,
+    css: Vec<String>,
+}
+
+impl ViteManifest {
+    /// Parse the manifest, `integrity` returns the SHA-384 integrity metadata of an emitted file.
+    pub(crate) fn parse(
+        json: &str,
+        integrity: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ViteManifestError> {
+        let value = serde_json::from_str::<Value>(json)
+            .map_err(|err| ViteManifestError::new(err.to_string()))?;
+        let entries = value
+            .as_object()
+            .ok_or_else(|| ViteManifestError::new("expected an object"))?;
+
+        let mut chunks = HashMap::new();
+        for (name, chunk) in entries {
+            let file = chunk
+                .get("file")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ViteManifestError::new(format!("`{name}` has no `file`")))?;
+            let list = |key: &str| -> Result<Vec<String>, ViteManifestError> {
+                let Some(list) = chunk.get(key) else {
+                    return Ok(Vec::new());
+                };
+                list.as_array()
+                    .and_then(|list| {
+                        list.iter()
+                            .map(|item| item.as_str().map(str::to_owned))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        ViteManifestError::new(format!("`{name}.{key}` is not a list of strings"))
+                    })
+            };
+
+            chunks.insert(
+                name.clone(),
+                Chunk {
+                    file: file.to_owned(),
+                    imports: list("imports")?,
+                    css: list("css")?,
+                },
+            );
+        }
+
+        let integrity = chunks
+            .values()
+            .flat_map(|chunk| std::iter::once(&chunk.file).chain(&chunk.css))
+            .filter_map(|file| Some((file.clone(), integrity(file)?)))
+            .collect();
+
+        Ok(Self {
+            chunks,
+            integrity,
+            base: "/".to_owned(),
+        })
+    }
+
+    /// The public base path the files are served from, the `base` option of the Vite config.
+    ///
+    /// Defaults to `/`.
+    pub fn base(mut self, base: &str) -> Self {
+        self.base = format!("{}/", base.trim_end_matches('/'));
+        self
+    }
+
+    /// Resolve the entry point `name`, e.g. `src/main.ts`, into the tags that load it.
+    ///
+    /// Returns `None` if the manifest has no such entry.
+    pub fn entry(&self, name: &str) -> Option<ViteEntry> {
+        let chunk = self.chunks.get(name)?;
+
+        let mut entry = ViteEntry::default();
+        let mut visited = Vec::new();
+        self.collect_imports(chunk, &mut visited, &mut entry);
+
+        let asset = self.asset(&chunk.file);
+        if chunk.file.ends_with(".css") {
+            entry.stylesheets.push(asset);
+        } else {
+            entry.scripts.push(asset);
+        }
+        for css in &chunk.css {
+            entry.add_stylesheet(self.asset(css));
+        }
+
+        Some(entry)
+    }
+
+    // Add the static imports of `chunk` as module preloads, including their stylesheets.
+    fn collect_imports<'a>(
+        &'a self,
+        chunk: &'a Chunk,
+        visited: &mut Vec<&'a str>,
+        entry: &mut ViteEntry,
+    ) {
+        for import in &chunk.imports {
+            if visited.contains(&import.as_str()) {
+                continue;
+            }
+            visited.push(import);
+
+            let Some(imported) = self.chunks.get(import) else {
+                continue;
+            };
+            self.collect_imports(imported, visited, entry);
+            entry.preloads.push(self.asset(&imported.file));
+            for css in &imported.css {
+                entry.add_stylesheet(self.asset(css));
+            }
+        }
+    }
+
+    fn asset(&self, file: &str) -> ViteAsset {
+        ViteAsset {
+            url: format!("{}{file}", self.base),
+            integrity: self.integrity.get(file).cloned(),
+        }
+    }
+}
+
+/// The files needed to load an entry point of a [`ViteManifest`].
+///
+/// Formatting it with [`Display`](fmt::Display) writes the HTML tags, stylesheets first:
+///
+/// ```html
+/// <link rel="stylesheet" href="/assets/main-CN0ZJl4k.css" integrity="sha384-…" crossorigin>
+/// <link rel="modulepreload" href="/assets/shared-B7PI925R.js" integrity="sha384-…" crossorigin>
+/// <script type="module" src="/assets/main-4GNSnfF5.js" integrity="sha384-…" crossorigin></script>
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ViteEntry {
+    scripts: Vec<ViteAsset>,
+    stylesheets: Vec<ViteAsset>,
+    preloads: Vec<ViteAsset>,
+}
+
+impl ViteEntry {
+    fn add_stylesheet(&mut self, asset: ViteAsset) {
+        if !self.stylesheets.contains(&asset) {
+            self.stylesheets.push(asset);
+        }
+    }
+
+    /// The module scripts to load, the entry point itself.
+    pub fn scripts(&self) -> &[ViteAsset] {
+        &self.scripts
+    }
+
+    /// The stylesheets of the entry point and of all chunks it imports.
+    pub fn stylesheets(&self) -> &[ViteAsset] {
+        &self.stylesheets
+    }
+
+    /// The chunks the entry point imports, to be loaded with `rel="modulepreload"`.
+    pub fn preloads(&self) -> &[ViteAsset] {
+        &self.preloads
+    }
+}
+
+impl fmt::Display for ViteEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for asset in &self.stylesheets {
+            tag(f, r#"<link rel="stylesheet""#, "href", asset, ">")?;
+        }
+        for asset in &self.preloads {
+            tag(f, r#"<link rel="modulepreload""#, "href", asset, ">")?;
+        }
+        for asset in &self.scripts {
+            tag(f, r#"<script type="module""#, "src", asset, "></script>")?;
+        }
+        Ok(())
+    }
+}
+
+// Write a tag with the URL of `asset` as `attribute` and its integrity, one per line.
+fn tag(
+    f: &mut fmt::Formatter<'_>,
+    start: &str,
+    attribute: &str,
+    asset: &ViteAsset,
+    end: &str,
+) -> fmt::Result {
+    write!(f, r#"{start} {attribute}="{}""#, escape(&asset.url))?;
+    if let Some(integrity) = &asset.integrity {
+        write!(f, r#" integrity="{}" crossorigin"#, escape(integrity))?;
+    }
+    writeln!(f, "{end}")
+}
+
+/// A file emitted by Vite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViteAsset {
+    url: String,
+    integrity: Option<String>,
+}
+
+impl ViteAsset {
+    /// The URL of the file, starting with the base path.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The SHA-384 Subresource Integrity of the file, if the [`ServeDir`](crate::ServeDir)
+    /// has digests, see [`ServeDir::with_digests`](crate::ServeDir::with_digests).
+    pub fn integrity(&self) -> Option<&str> {
+        self.integrity.as_deref()
+    }
+}
+
+fn escape(attribute: &str) -> String {
+    attribute
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = include_str!("../tests/vite/.vite/manifest.json");
+
+    #[test]
+    fn entry() {
+        let manifest = ViteManifest::parse(MANIFEST, |file| {
+            file.ends_with(".js").then(|| format!("sha384-{file}"))
+        })
+        .unwrap();
+
+        let entry = manifest.entry("src/main.ts").unwrap();
+        assert_eq!(entry.scripts()[0].url(), "/assets/main-4GNSnfF5.js");
+        assert_eq!(
+            entry.scripts()[0].integrity(),
+            Some("sha384-assets/main-4GNSnfF5.js")
+        );
+        // dynamic imports aren't preloaded
+        assert_eq!(entry.preloads().len(), 1);
+        assert_eq!(entry.preloads()[0].url(), "/assets/shared-B7PI925R.js");
+        let stylesheets = entry
+            .stylesheets()
+            .iter()
+            .map(ViteAsset::url)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stylesheets,
+            ["/assets/shared-ChJ_j-JJ.css", "/assets/main-CN0ZJl4k.css"]
+        );
+        assert_eq!(
+            entry.to_string(),
+            "<link rel=\"stylesheet\" href=\"/assets/shared-ChJ_j-JJ.css\">\n\
+             <link rel=\"stylesheet\" href=\"/assets/main-CN0ZJl4k.css\">\n\
+             <link rel=\"modulepreload\" href=\"/assets/shared-B7PI925R.js\" \
+             integrity=\"sha384-assets/shared-B7PI925R.js\" crossorigin>\n\
+             <script type=\"module\" src=\"/assets/main-4GNSnfF5.js\" \
+             integrity=\"sha384-assets/main-4GNSnfF5.js\" crossorigin></script>\n"
+        );
+
+        let entry = manifest.base("/app").entry("src/theme.css").unwrap();
+        assert!(entry.scripts().is_empty());
+        assert_eq!(
+            entry.stylesheets()[0].url(),
+            "/app/assets/theme-DmE1a1PZ.css"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |json| ViteManifest::parse(json, |_| None).unwrap_err().to_string();
+        assert!(parse("[]").contains("expected an object"));
+        assert!(parse(r#"{"main.js": {}}"#).contains("`main.js` has no `file`"));
+        assert!(
+            parse(r#"{"main.js": {"file": "main.js", "css": "main.css"}}"#)
+                .contains("`main.js.css` is not a list of strings")
+        );
+        assert!(parse("{").starts_with("invalid Vite manifest: "));
+    }
+}
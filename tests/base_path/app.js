@@ -0,0 +1 @@
+fetch("/api/config");
@@ -0,0 +1,11 @@
+#[test]
+fn ui() {
+    // The UI tests are built in a separate crate, point them to the fixtures of this one.
+    std::env::set_var(
+        "FIXTURES_DIR",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests"),
+    );
+
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,13 @@
+use include_dir::Dir;
+use tower_serve_static::include_assets;
+
+static ASSETS_DIR: Dir<'static> = include_assets!(
+    "$FIXTURES_DIR/assets",
+    exclude = ["image.png", "subfolder"],
+    budget = {
+        total = 64,
+        "*.txt" = 16,
+    },
+);
+
+fn main() {}
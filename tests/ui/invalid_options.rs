@@ -0,0 +1,20 @@
+use include_dir::Dir;
+use tower_serve_static::include_assets;
+
+static NOT_A_PATTERN: Dir<'static> = include_assets!("$FIXTURES_DIR/assets", include = [1]);
+
+static NOT_A_BUDGET: Dir<'static> = include_assets!(
+    "$FIXTURES_DIR/assets",
+    budget = { js = "250 KiB" },
+);
+
+static NOT_A_SIZE: Dir<'static> = include_assets!(
+    "$FIXTURES_DIR/assets",
+    max_file_size = "1 parsec",
+);
+
+static UNKNOWN_OPTION: Dir<'static> = include_assets!("$FIXTURES_DIR/assets", filter = []);
+
+static NOT_A_DIRECTORY: Dir<'static> = include_assets!("$FIXTURES_DIR/missing");
+
+fn main() {}
@@ -0,0 +1,11 @@
+use include_dir::Dir;
+use tower_serve_static::include_assets;
+
+static ASSETS_DIR: Dir<'static> = include_assets!(
+    "$FIXTURES_DIR/assets",
+    exclude = ["*.json"],
+    max_file_size = "4 KiB",
+    report_skipped = true,
+);
+
+fn main() {}
@@ -0,0 +1 @@
+import{s}from"./shared-B7PI925R.js";s("lazy");
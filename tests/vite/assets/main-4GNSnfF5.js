@@ -0,0 +1 @@
+import{s as o}from"./shared-B7PI925R.js";o("main");import("./lazy-BW0zE2qX.js");
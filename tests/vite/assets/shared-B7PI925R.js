@@ -0,0 +1 @@
+function s(n){console.log(n)}export{s};
@@ -0,0 +1 @@
+const module = await WebAssembly.compileStreaming(fetch("app_bg.wasm"));